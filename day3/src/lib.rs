@@ -1,122 +1,666 @@
 pub mod day3 {
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, HashMap, HashSet};
+    use std::convert::TryFrom;
+    use std::fmt;
     use std::fs::File;
     use std::io::{self, BufRead};
+    use std::ops::RangeInclusive;
     use std::path::Path;
 
+    /// A rectangular map of open squares (`.`) and trees (`#`).
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Forest {
+        trees: Vec<Vec<bool>>,
+        width: usize,
+    }
+
+    /// Why a map could not be read. Rows and columns are 1-based, as in the file.
+    #[derive(Debug)]
+    pub enum ForestError {
+        Io(io::Error),
+        Empty,
+        Ragged {
+            row: usize,
+            expected: usize,
+            found: usize,
+        },
+        UnknownGlyph {
+            row: usize,
+            column: usize,
+            glyph: char,
+        },
+    }
+
+    impl fmt::Display for ForestError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ForestError::Io(e) => write!(f, "cannot read map: {}", e),
+                ForestError::Empty => write!(f, "map is empty"),
+                ForestError::Ragged {
+                    row,
+                    expected,
+                    found,
+                } => write!(
+                    f,
+                    "row {} is {} squares wide, expected {}",
+                    row, found, expected
+                ),
+                ForestError::UnknownGlyph { row, column, glyph } => write!(
+                    f,
+                    "unknown square {:?} at row {}, column {}",
+                    glyph, row, column
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for ForestError {}
+
+    /// A start location, as (column, row), which is not on the map.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct OutsideMap {
+        pub location: (i32, i32),
+        pub width: usize,
+        pub height: usize,
+    }
+
+    impl fmt::Display for OutsideMap {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "start {:?} is outside the {}x{} map",
+                self.location, self.width, self.height
+            )
+        }
+    }
+
+    impl std::error::Error for OutsideMap {}
+
+    impl From<io::Error> for ForestError {
+        fn from(e: io::Error) -> ForestError {
+            ForestError::Io(e)
+        }
+    }
+
+    impl Forest {
+        pub fn from_reader(input: &mut dyn BufRead) -> Result<Forest, ForestError> {
+            let rows = input
+                .lines()
+                .map(|line| line.map(|row| row.chars().collect()))
+                .collect::<io::Result<Vec<Vec<char>>>>()?;
+            Forest::try_from(rows)
+        }
+
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        pub fn height(&self) -> usize {
+            self.trees.len()
+        }
+
+        /// Whether there is a tree at `location` (column, row). Squares off
+        /// the map are open.
+        pub fn is_tree(&self, location: (i32, i32)) -> bool {
+            if location.0 < 0 || location.1 < 0 {
+                return false;
+            }
+            self.trees
+                .get(location.1 as usize)
+                .and_then(|row| row.get(location.0 as usize))
+                .copied()
+                .unwrap_or(false)
+        }
+    }
+
+    impl TryFrom<Vec<Vec<char>>> for Forest {
+        type Error = ForestError;
+
+        fn try_from(map: Vec<Vec<char>>) -> Result<Forest, ForestError> {
+            let width = match map.first() {
+                Some(row) if !row.is_empty() => row.len(),
+                _ => return Err(ForestError::Empty),
+            };
+
+            let mut trees = Vec::with_capacity(map.len());
+            for (r, row) in map.iter().enumerate() {
+                if row.len() != width {
+                    return Err(ForestError::Ragged {
+                        row: r + 1,
+                        expected: width,
+                        found: row.len(),
+                    });
+                }
+                let squares = row
+                    .iter()
+                    .enumerate()
+                    .map(|(c, &glyph)| match glyph {
+                        '.' => Ok(false),
+                        '#' => Ok(true),
+                        _ => Err(ForestError::UnknownGlyph {
+                            row: r + 1,
+                            column: c + 1,
+                            glyph,
+                        }),
+                    })
+                    .collect::<Result<Vec<bool>, ForestError>>()?;
+                trees.push(squares);
+            }
+
+            Ok(Forest { trees, width })
+        }
+    }
+
+    /// How the toboggan behaves when a move would take it past an edge of the map.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Boundary {
+        /// The map repeats, so the toboggan re-enters from the opposite edge.
+        Wrap,
+        /// The toboggan is held at the edge it tried to cross.
+        Clamp,
+        /// The run ends as soon as the toboggan leaves the map.
+        Stop,
+    }
+
+    /// Which cells of a move count towards the encountered trees.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Traversal {
+        /// Only the cell the toboggan lands on after each move.
+        Landing,
+        /// Every cell the straight line between two landing spots passes
+        /// through. A line crossing exactly through a corner does not brush
+        /// the two cells meeting at that corner.
+        Supercover,
+    }
+
+    /// A planned way down the map.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Route {
+        /// Landing cells from the start to the bottom row, both included.
+        pub path: Vec<(i32, i32)>,
+        /// Trees hit along the path, not counting the start.
+        pub trees: i64,
+    }
+
     pub struct Toboggan {
-        map: Vec<Vec<char>>,
+        forest: Forest,
+        start: (i32, i32),
         current_location: (i32, i32),
-        encountered_trees: i64,
+        horizontal: Boundary,
+        vertical: Boundary,
+        traversal: Traversal,
+        visited: HashSet<(i32, i32)>,
+        finished: bool,
+        encountered_trees: Vec<(i32, i32)>,
     }
 
     impl Toboggan {
-        pub fn new(map: Vec<Vec<char>>) -> Toboggan {
+        pub fn new(forest: Forest) -> Toboggan {
             Toboggan {
-                map: map,
+                forest,
+                start: (0, 0),
                 current_location: (0, 0),
-                encountered_trees: 0,
+                horizontal: Boundary::Wrap,
+                vertical: Boundary::Stop,
+                traversal: Traversal::Landing,
+                visited: HashSet::new(),
+                finished: false,
+                encountered_trees: Vec::new(),
+            }
+        }
+
+        /// Sets the boundary mode for each axis. The default is to wrap
+        /// horizontally and stop at the bottom of the map.
+        pub fn with_boundaries(mut self, horizontal: Boundary, vertical: Boundary) -> Toboggan {
+            self.horizontal = horizontal;
+            self.vertical = vertical;
+            self
+        }
+
+        /// Sets which cells of each move are checked for trees. The default
+        /// only checks landing cells.
+        pub fn with_traversal(mut self, traversal: Traversal) -> Toboggan {
+            self.traversal = traversal;
+            self
+        }
+
+        /// Starts the run from `location` (column, row) instead of the
+        /// top-left corner. The location has to be on the map.
+        pub fn starting_at(mut self, location: (i32, i32)) -> Result<Toboggan, OutsideMap> {
+            let on_map =
+                (0..self.width()).contains(&location.0) && (0..self.height()).contains(&location.1);
+            if !on_map {
+                return Err(OutsideMap {
+                    location,
+                    width: self.forest.width(),
+                    height: self.forest.height(),
+                });
             }
+            self.start = location;
+            self.current_location = location;
+            Ok(self)
         }
 
         pub fn traverse(&mut self, slope: (i32, i32)) {
+            self.visited.insert(self.current_location);
             while !self.traversed() {
                 self.proceed(slope);
-                self.watch();
             }
         }
 
         fn traversed(&self) -> bool {
-            self.current_location.1 as usize >= self.map.len() - 1
+            if self.finished {
+                return true;
+            }
+            self.vertical != Boundary::Wrap && self.current_location.1 >= self.height() - 1
         }
 
         fn proceed(&mut self, slope: (i32, i32)) {
-            let map_seed_width = self.map.get(0).unwrap().len() as i32;
-            self.current_location.0 = (self.current_location.0 + slope.0) % map_seed_width;
-            self.current_location.1 += slope.1;
-            // println!("({}, {})", self.current_location.0, self.current_location.1);
+            let (mut cells, on_map) = self.brushed(self.current_location, slope);
+            let landing = if on_map { cells.last().copied() } else { None };
+
+            match landing {
+                Some(cell) if self.visited.insert(cell) => self.current_location = cell,
+                // Came back to a spot already visited, for instance a wrapped
+                // run returning to its start.
+                Some(_) => {
+                    cells.pop();
+                    self.finished = true;
+                }
+                // Left the map, or did not move at all.
+                None => self.finished = true,
+            }
+
+            for cell in cells {
+                self.watch(cell);
+            }
+        }
+
+        /// Cells checked for trees on a move from `origin`, ending with the
+        /// landing cell. The flag is false when the move leaves the map, in
+        /// which case only the cells before leaving are returned. A move
+        /// past the range of `i32` leaves the map whatever the boundaries.
+        fn brushed(&self, origin: (i32, i32), slope: (i32, i32)) -> (Vec<(i32, i32)>, bool) {
+            let offsets = match self.traversal {
+                Traversal::Landing => vec![slope],
+                Traversal::Supercover => supercover(slope),
+            };
+
+            let mut cells: Vec<(i32, i32)> = Vec::new();
+            for offset in offsets {
+                let x = origin
+                    .0
+                    .checked_add(offset.0)
+                    .and_then(|x| resolve(self.horizontal, x, self.width()));
+                let y = origin
+                    .1
+                    .checked_add(offset.1)
+                    .and_then(|y| resolve(self.vertical, y, self.height()));
+                match (x, y) {
+                    // A clamped line can stay in the same cell for several steps.
+                    (Some(x), Some(y)) if cells.last() == Some(&(x, y)) => {}
+                    (Some(x), Some(y)) => cells.push((x, y)),
+                    _ => return (cells, false),
+                }
+            }
+
+            (cells, true)
         }
 
-        fn watch(&mut self) {
-            let row = self.map.get(self.current_location.1 as usize).unwrap();
-            let obj = row.get(self.current_location.0 as usize).unwrap();
-            // println!("{}", obj);
-            if *obj == '#' {
-                self.encountered_trees += 1;
+        /// Plans the route from the start to the bottom row which hits the
+        /// fewest trees, using only the given moves. Boundaries and traversal
+        /// mode apply to every move as they do in [`Toboggan::traverse`], and
+        /// moves leaving the map are not allowed. Returns `None` when the
+        /// bottom row cannot be reached.
+        pub fn plan_route(&self, moves: &[(i32, i32)]) -> Option<Route> {
+            let mut trees: HashMap<(i32, i32), i64> = HashMap::new();
+            let mut previous: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+            let mut queue = BinaryHeap::new();
+
+            trees.insert(self.start, 0);
+            queue.push(Reverse((0, self.start)));
+
+            while let Some(Reverse((cost, location))) = queue.pop() {
+                if cost > trees[&location] {
+                    continue;
+                }
+                if location.1 == self.height() - 1 {
+                    return Some(Route {
+                        path: unwind(&previous, location),
+                        trees: cost,
+                    });
+                }
+
+                for &slope in moves {
+                    let (cells, on_map) = self.brushed(location, slope);
+                    let landing = match cells.last() {
+                        Some(&landing) if on_map && landing != location => landing,
+                        _ => continue,
+                    };
+                    let next_cost =
+                        cost + cells.iter().filter(|&&c| self.forest.is_tree(c)).count() as i64;
+                    if trees.get(&landing).is_none_or(|&known| next_cost < known) {
+                        trees.insert(landing, next_cost);
+                        previous.insert(landing, location);
+                        queue.push(Reverse((next_cost, landing)));
+                    }
+                }
             }
+
+            None
+        }
+
+        fn watch(&mut self, location: (i32, i32)) {
+            if self.forest.is_tree(location) {
+                self.encountered_trees.push(location);
+            }
+        }
+
+        fn width(&self) -> i32 {
+            self.forest.width() as i32
+        }
+
+        fn height(&self) -> i32 {
+            self.forest.height() as i32
+        }
+
+        pub fn get_start(&self) -> (i32, i32) {
+            self.start
+        }
+
+        pub fn get_current_location(&self) -> (i32, i32) {
+            self.current_location
         }
 
         pub fn get_encountered_trees(&self) -> i64 {
-            self.encountered_trees
+            self.encountered_trees.len() as i64
+        }
+
+        /// Locations of the trees hit so far, in the order they were hit.
+        pub fn get_trees_hit(&self) -> &[(i32, i32)] {
+            &self.encountered_trees
+        }
+    }
+
+    fn unwind(previous: &HashMap<(i32, i32), (i32, i32)>, end: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut path = vec![end];
+        while let Some(&location) = previous.get(path.last().unwrap()) {
+            path.push(location);
+        }
+        path.reverse();
+        path
+    }
+
+    fn resolve(boundary: Boundary, position: i32, size: i32) -> Option<i32> {
+        if size == 0 {
+            return None;
+        }
+        match boundary {
+            Boundary::Wrap => Some(position.rem_euclid(size)),
+            Boundary::Clamp => Some(position.clamp(0, size - 1)),
+            Boundary::Stop if (0..size).contains(&position) => Some(position),
+            Boundary::Stop => None,
+        }
+    }
+
+    /// Offsets of the cells a line from the origin to `slope` passes through,
+    /// ending with `slope` itself. The origin is not included.
+    fn supercover(slope: (i32, i32)) -> Vec<(i32, i32)> {
+        // Widened so that neither the absolute values nor the decision overflow.
+        let (nx, ny) = (i64::from(slope.0).abs(), i64::from(slope.1).abs());
+        let (sx, sy) = (i64::from(slope.0.signum()), i64::from(slope.1.signum()));
+        let (mut ix, mut iy) = (0, 0);
+        let mut cells = Vec::new();
+
+        while ix < nx || iy < ny {
+            // Compares where the line crosses the next vertical and the next
+            // horizontal cell border; both at once means a corner.
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if decision == 0 {
+                ix += 1;
+                iy += 1;
+            } else if decision < 0 {
+                ix += 1;
+            } else {
+                iy += 1;
+            }
+            cells.push(((ix * sx) as i32, (iy * sy) as i32));
         }
+
+        cells
     }
 
-    pub fn traverse_toboggan<P>(input_file: P, slope: (i32, i32)) -> i64
+    pub fn traverse_toboggan<P>(input_file: P, slope: (i32, i32)) -> Result<i64, ForestError>
     where
         P: AsRef<Path>,
     {
-        let map = parse_input(input_file);
-        let mut toboggan = Toboggan::new(map);
+        Ok(count_trees(&parse_input(input_file)?, slope))
+    }
+
+    fn count_trees(forest: &Forest, slope: (i32, i32)) -> i64 {
+        let mut toboggan = Toboggan::new(forest.clone());
         toboggan.traverse(slope);
-        return toboggan.get_encountered_trees();
+        toboggan.get_encountered_trees()
     }
 
-    fn parse_input<P>(input_file: P) -> Vec<Vec<char>>
+    pub fn parse_input<P>(input_file: P) -> Result<Forest, ForestError>
     where
         P: AsRef<Path>,
     {
-        let mut map: Vec<Vec<char>> = Vec::new();
+        let file = File::open(input_file)?;
+        Forest::from_reader(&mut io::BufReader::new(file))
+    }
+
+    /// How often each tree of a map was hit over many runs.
+    pub struct Heatmap {
+        forest: Forest,
+        hits: Vec<Vec<u32>>,
+    }
+
+    impl Heatmap {
+        /// Shades from a tree hit least often to one hit most often.
+        const SHADES: [char; 7] = ['-', '=', '+', '*', '#', '%', '@'];
+
+        pub fn new(forest: Forest) -> Heatmap {
+            let hits = vec![vec![0; forest.width()]; forest.height()];
+            Heatmap { forest, hits }
+        }
+
+        /// Runs a default toboggan down the map for every slope.
+        pub fn from_slopes<I>(forest: &Forest, slopes: I) -> Heatmap
+        where
+            I: IntoIterator<Item = (i32, i32)>,
+        {
+            let mut heatmap = Heatmap::new(forest.clone());
+            for slope in slopes {
+                let mut toboggan = Toboggan::new(forest.clone());
+                toboggan.traverse(slope);
+                heatmap.add_run(&toboggan);
+            }
+            heatmap
+        }
 
-        if let Ok(lines) = read_lines(input_file) {
-            for line in lines {
-                if let Ok(row) = line {
-                    map.push(row.chars().collect());
+        /// Accumulates the trees hit by a run, so runs with custom boundaries,
+        /// traversal or start can be added too.
+        pub fn add_run(&mut self, toboggan: &Toboggan) {
+            for &(x, y) in toboggan.get_trees_hit() {
+                if let Some(cell) = self
+                    .hits
+                    .get_mut(y as usize)
+                    .and_then(|row| row.get_mut(x as usize))
+                {
+                    *cell += 1;
                 }
             }
         }
 
-        return map;
+        pub fn hits(&self, location: (i32, i32)) -> u32 {
+            self.hits
+                .get(location.1 as usize)
+                .and_then(|row| row.get(location.0 as usize))
+                .copied()
+                .unwrap_or(0)
+        }
+
+        pub fn max_hits(&self) -> u32 {
+            self.hits.iter().flatten().copied().max().unwrap_or(0)
+        }
+
+        /// Trees hit at least once, most often hit first.
+        pub fn obstacles(&self) -> Vec<((i32, i32), u32)> {
+            let mut obstacles: Vec<((i32, i32), u32)> = self
+                .hits
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, &hits)| hits > 0)
+                        .map(move |(x, &hits)| ((x as i32, y as i32), hits))
+                })
+                .collect();
+            obstacles.sort_by_key(|&((x, y), hits)| (Reverse(hits), y, x));
+            obstacles
+        }
+
+        /// One character per square: a space for open ground, `.` for a tree
+        /// never hit, and `-=+*#%@` from least to most hit.
+        pub fn to_ascii(&self) -> String {
+            let max = self.max_hits();
+            let mut out = String::new();
+            for (y, row) in self.hits.iter().enumerate() {
+                for (x, &hits) in row.iter().enumerate() {
+                    let shade = if !self.forest.is_tree((x as i32, y as i32)) {
+                        ' '
+                    } else if hits == 0 {
+                        '.'
+                    } else {
+                        let levels = Heatmap::SHADES.len() as u32;
+                        let level = (hits * levels).div_ceil(max);
+                        Heatmap::SHADES[level as usize - 1]
+                    };
+                    out.push(shade);
+                }
+                out.push('\n');
+            }
+            out
+        }
+
+        /// Plain (P2) PGM image, one pixel per square: open ground is 0 and
+        /// a tree is 1 plus its hit count, so trees never hit stay apart from
+        /// open ground and the most hit trees are white. Counts too high for
+        /// the format's maximum of 65535 are scaled down, and lines are
+        /// wrapped at 70 characters as the format asks.
+        pub fn to_pgm(&self) -> String {
+            const MAX_GREY: u64 = 65535;
+            const LINE_LENGTH: usize = 70;
+
+            let max = u64::from(self.max_hits());
+            let grey = |hits: u32| {
+                let hits = u64::from(hits);
+                if max < MAX_GREY {
+                    1 + hits
+                } else {
+                    1 + (hits * (MAX_GREY - 1)).div_ceil(max)
+                }
+            };
+            let mut out = format!(
+                "P2\n{} {}\n{}\n",
+                self.forest.width(),
+                self.forest.height(),
+                grey(self.max_hits())
+            );
+            for (y, row) in self.hits.iter().enumerate() {
+                let mut line = String::new();
+                for (x, &hits) in row.iter().enumerate() {
+                    let pixel = if self.forest.is_tree((x as i32, y as i32)) {
+                        grey(hits).to_string()
+                    } else {
+                        String::from("0")
+                    };
+                    if !line.is_empty() && line.len() + 1 + pixel.len() > LINE_LENGTH {
+                        out.push_str(&line);
+                        out.push('\n');
+                        line.clear();
+                    }
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&pixel);
+                }
+                out.push_str(&line);
+                out.push('\n');
+            }
+            out
+        }
     }
 
-    fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-    where
-        P: AsRef<Path>,
-    {
-        let file = File::open(filename)?;
-        Ok(io::BufReader::new(file).lines())
+    /// Every slope with its right and down steps in the given ranges, except
+    /// standing still.
+    pub fn slopes(right: RangeInclusive<i32>, down: RangeInclusive<i32>) -> Vec<(i32, i32)> {
+        down.flat_map(|dy| right.clone().map(move |dx| (dx, dy)))
+            .filter(|&slope| slope != (0, 0))
+            .collect()
     }
 
-    pub fn pentathlon<P>(input_file: P, slopes: Vec<(i32, i32)>) -> i64
+    pub fn pentathlon<P>(input_file: P, slopes: Vec<(i32, i32)>) -> Result<i64, ForestError>
     where
         P: AsRef<Path>,
     {
-        return slopes
+        let forest = parse_input(input_file)?;
+        Ok(slopes
             .iter()
-            .map(|&slope| traverse_toboggan(&input_file, slope))
-            .product();
+            .map(|&slope| count_trees(&forest, slope))
+            .product())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::day3;
+    use super::day3::{
+        Boundary, Forest, ForestError, Heatmap, OutsideMap, Route, Toboggan, Traversal,
+    };
 
     #[test]
     fn sample_input() {
-        assert_eq!(day3::traverse_toboggan("./sample.input", (3, 1)), 7);
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (3, 1)).unwrap(),
+            7
+        );
     }
 
     #[test]
     fn day3_input() {
-        assert_eq!(day3::traverse_toboggan("./day3.input", (3, 1)), 284);
+        assert_eq!(
+            day3::traverse_toboggan("./day3.input", (3, 1)).unwrap(),
+            284
+        );
     }
 
     #[test]
     fn slopes() {
-        assert_eq!(day3::traverse_toboggan("./sample.input", (1, 1)), 2);
-        assert_eq!(day3::traverse_toboggan("./sample.input", (3, 1)), 7);
-        assert_eq!(day3::traverse_toboggan("./sample.input", (5, 1)), 3);
-        assert_eq!(day3::traverse_toboggan("./sample.input", (7, 1)), 4);
-        assert_eq!(day3::traverse_toboggan("./sample.input", (1, 2)), 2);
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (1, 1)).unwrap(),
+            2
+        );
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (3, 1)).unwrap(),
+            7
+        );
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (5, 1)).unwrap(),
+            3
+        );
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (7, 1)).unwrap(),
+            4
+        );
+        assert_eq!(
+            day3::traverse_toboggan("./sample.input", (1, 2)).unwrap(),
+            2
+        );
     }
 
     #[test]
@@ -125,7 +669,8 @@ mod tests {
             day3::pentathlon(
                 "./sample.input",
                 vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
-            ),
+            )
+            .unwrap(),
             336
         );
     }
@@ -133,11 +678,278 @@ mod tests {
     #[test]
     fn day3_final_score() {
         assert_eq!(
-            day3::pentathlon(
-                "./day3.input",
-                vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
-            ),
+            day3::pentathlon("./day3.input", vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]).unwrap(),
             3510149120
         );
     }
+
+    #[test]
+    fn moves_past_the_i32_range_leave_the_map() {
+        let mut toboggan = Toboggan::new(sample()).starting_at((5, 0)).unwrap();
+        toboggan.traverse((i32::MAX, 1));
+        assert_eq!(toboggan.get_current_location(), (5, 0));
+        assert_eq!(toboggan.get_encountered_trees(), 0);
+
+        let mut toboggan = Toboggan::new(sample())
+            .with_boundaries(Boundary::Clamp, Boundary::Stop)
+            .starting_at((5, 0))
+            .unwrap();
+        toboggan.traverse((i32::MAX, 1));
+        assert_eq!(toboggan.get_current_location(), (5, 0));
+    }
+
+    #[test]
+    fn starts_off_the_map_are_rejected() {
+        let error = Toboggan::new(sample()).starting_at((11, 0)).err().unwrap();
+        assert_eq!(
+            error,
+            OutsideMap {
+                location: (11, 0),
+                width: 11,
+                height: 11
+            }
+        );
+        assert_eq!(error.to_string(), "start (11, 0) is outside the 11x11 map");
+        assert!(Toboggan::new(sample()).starting_at((0, -1)).is_err());
+    }
+
+    #[test]
+    fn moving_left_wraps_around_the_left_edge() {
+        let mut toboggan = Toboggan::new(sample()).starting_at((10, 0)).unwrap();
+        toboggan.traverse((-3, 1));
+        assert_eq!(toboggan.get_encountered_trees(), 3);
+        assert_eq!(toboggan.get_current_location(), (2, 10));
+    }
+
+    #[test]
+    fn clamped_run_slides_along_the_right_edge() {
+        let mut toboggan = Toboggan::new(sample()).with_boundaries(Boundary::Clamp, Boundary::Stop);
+        toboggan.traverse((3, 1));
+        assert_eq!(toboggan.get_encountered_trees(), 5);
+        assert_eq!(toboggan.get_current_location(), (10, 10));
+    }
+
+    #[test]
+    fn stopped_run_ends_when_leaving_the_map() {
+        let mut toboggan = Toboggan::new(sample()).with_boundaries(Boundary::Stop, Boundary::Stop);
+        toboggan.traverse((3, 1));
+        assert_eq!(toboggan.get_encountered_trees(), 1);
+        assert_eq!(toboggan.get_current_location(), (9, 3));
+    }
+
+    #[test]
+    fn clamped_bottom_lands_on_the_last_row() {
+        let mut toboggan = Toboggan::new(sample()).with_boundaries(Boundary::Wrap, Boundary::Clamp);
+        toboggan.traverse((1, 3));
+        assert_eq!(toboggan.get_encountered_trees(), 1);
+        assert_eq!(toboggan.get_current_location(), (4, 10));
+    }
+
+    #[test]
+    fn wrapped_run_ends_before_returning_to_its_start() {
+        let mut toboggan = Toboggan::new(sample()).with_boundaries(Boundary::Wrap, Boundary::Wrap);
+        toboggan.traverse((1, 3));
+        assert_eq!(toboggan.get_encountered_trees(), 2);
+        assert_eq!(toboggan.get_current_location(), (10, 8));
+    }
+
+    #[test]
+    fn supercover_counts_rows_jumped_over() {
+        let mut toboggan = Toboggan::new(sample()).with_traversal(Traversal::Supercover);
+        toboggan.traverse((1, 2));
+        assert_eq!(toboggan.get_encountered_trees(), 7);
+    }
+
+    #[test]
+    fn supercover_counts_cells_between_landings() {
+        let mut toboggan = Toboggan::new(sample()).with_traversal(Traversal::Supercover);
+        toboggan.traverse((3, 1));
+        assert_eq!(toboggan.get_encountered_trees(), 13);
+
+        let mut toboggan = Toboggan::new(sample()).with_traversal(Traversal::Supercover);
+        toboggan.traverse((2, 3));
+        assert_eq!(toboggan.get_encountered_trees(), 4);
+    }
+
+    #[test]
+    fn supercover_of_a_diagonal_only_touches_corners() {
+        let mut toboggan = Toboggan::new(sample()).with_traversal(Traversal::Supercover);
+        toboggan.traverse((1, 1));
+        assert_eq!(toboggan.get_encountered_trees(), 2);
+    }
+
+    #[test]
+    fn route_avoiding_every_tree() {
+        let toboggan = Toboggan::new(sample());
+        let forest = sample();
+
+        let route = toboggan.plan_route(&[(0, 1), (-1, 1), (1, 1)]).unwrap();
+
+        assert_eq!(route.trees, 0);
+        assert_eq!(route.path.len(), 11);
+        assert_eq!(route.path[0], (0, 0));
+        for window in route.path.windows(2) {
+            let dx = (window[1].0 - window[0].0 + 11) % 11;
+            assert!(dx == 0 || dx == 1 || dx == 10);
+            assert_eq!(window[1].1, window[0].1 + 1);
+            assert!(!forest.is_tree(window[1]));
+        }
+    }
+
+    #[test]
+    fn route_with_a_single_move_matches_the_slope() {
+        let toboggan = Toboggan::new(sample());
+        assert_eq!(toboggan.plan_route(&[(3, 1)]).unwrap().trees, 7);
+    }
+
+    #[test]
+    fn route_straight_down() {
+        let toboggan = Toboggan::new(sample()).starting_at((2, 0)).unwrap();
+        let route = toboggan.plan_route(&[(0, 1)]).unwrap();
+        assert_eq!(route.trees, 3);
+        assert_eq!(route.path.first(), Some(&(2, 0)));
+        assert_eq!(route.path.last(), Some(&(2, 10)));
+    }
+
+    #[test]
+    fn no_route_when_moves_never_go_down() {
+        let toboggan = Toboggan::new(sample());
+        assert_eq!(toboggan.plan_route(&[(1, 0), (-1, 0)]), None::<Route>);
+    }
+
+    #[test]
+    fn empty_map_is_rejected() {
+        assert!(matches!(
+            Forest::from_reader(&mut "".as_bytes()),
+            Err(ForestError::Empty)
+        ));
+    }
+
+    #[test]
+    fn ragged_map_is_rejected() {
+        let error = Forest::from_reader(&mut "..#\n.#.\n#.\n...".as_bytes()).unwrap_err();
+        assert!(matches!(
+            error,
+            ForestError::Ragged {
+                row: 3,
+                expected: 3,
+                found: 2
+            }
+        ));
+        assert_eq!(error.to_string(), "row 3 is 2 squares wide, expected 3");
+    }
+
+    #[test]
+    fn unknown_glyph_is_rejected() {
+        let error = Forest::from_reader(&mut "..#\n.#.\n#O.".as_bytes()).unwrap_err();
+        assert!(matches!(
+            error,
+            ForestError::UnknownGlyph {
+                row: 3,
+                column: 2,
+                glyph: 'O'
+            }
+        ));
+        assert_eq!(error.to_string(), "unknown square 'O' at row 3, column 2");
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        assert!(matches!(
+            day3::parse_input("./missing.input"),
+            Err(ForestError::Io(_))
+        ));
+        assert!(matches!(
+            day3::traverse_toboggan("./missing.input", (3, 1)),
+            Err(ForestError::Io(_))
+        ));
+        assert!(matches!(
+            day3::pentathlon("./missing.input", vec![(1, 1)]),
+            Err(ForestError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn trees_hit_are_recorded_in_order() {
+        let mut toboggan = Toboggan::new(sample());
+        toboggan.traverse((1, 2));
+        assert_eq!(toboggan.get_trees_hit(), &[(1, 2), (3, 6)]);
+    }
+
+    #[test]
+    fn heatmap_accumulates_hits_over_slopes() {
+        let heatmap = Heatmap::from_slopes(&sample(), vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]);
+
+        let total: u32 = heatmap.obstacles().iter().map(|o| o.1).sum();
+        assert_eq!(total, 2 + 7 + 3 + 4 + 2);
+        assert_eq!(heatmap.hits((3, 6)), 1);
+        assert_eq!(heatmap.hits((0, 0)), 0);
+    }
+
+    #[test]
+    fn pgm_lines_stay_short() {
+        let forest = Forest::from_reader(&mut "#".repeat(40).as_bytes()).unwrap();
+        let pgm = Heatmap::new(forest).to_pgm();
+
+        assert!(pgm.lines().all(|line| line.len() <= 70));
+        assert_eq!(
+            pgm.lines().skip(3).collect::<Vec<&str>>().join(" "),
+            vec!["1"; 40].join(" ")
+        );
+    }
+
+    #[test]
+    fn pgm_scales_hit_counts_down_to_the_maximum_grey() {
+        let forest = Forest::from_reader(&mut "#.\n##".as_bytes()).unwrap();
+        let mut heatmap = Heatmap::new(forest.clone());
+        for _ in 0..70000 {
+            let mut toboggan = Toboggan::new(forest.clone());
+            toboggan.traverse((0, 1));
+            heatmap.add_run(&toboggan);
+        }
+
+        assert_eq!(heatmap.max_hits(), 70000);
+        assert_eq!(heatmap.to_pgm(), "P2\n2 2\n65535\n1 0\n65535 1\n");
+    }
+
+    #[test]
+    fn heatmap_lists_most_hit_trees_first() {
+        let heatmap = Heatmap::from_slopes(&sample(), day3::slopes(1..=7, 1..=2));
+        let obstacles = heatmap.obstacles();
+
+        assert_eq!(obstacles[0].1, heatmap.max_hits());
+        assert!(obstacles.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn slopes_cover_the_ranges() {
+        assert_eq!(
+            day3::slopes(-1..=1, 0..=1),
+            vec![(-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn heatmap_as_ascii() {
+        let heatmap = Heatmap::from_slopes(&sample(), vec![(1, 1), (1, 1), (3, 1)]);
+        let ascii = heatmap.to_ascii();
+        let rows: Vec<&str> = ascii.lines().collect();
+
+        assert_eq!(rows.len(), 11);
+        assert_eq!(rows[0], "  ..       ");
+        assert_eq!(rows[2], " .    *  . ");
+        assert_eq!(rows[5], "  . *@     ");
+        assert_eq!(rows[10], " .  .   * @");
+    }
+
+    #[test]
+    fn heatmap_as_pgm() {
+        let forest = Forest::from_reader(&mut "#.\n.#\n##".as_bytes()).unwrap();
+        let heatmap = Heatmap::from_slopes(&forest, vec![(1, 1), (0, 1)]);
+        assert_eq!(heatmap.to_pgm(), "P2\n2 3\n3\n1 0\n0 2\n3 1\n");
+    }
+
+    fn sample() -> Forest {
+        day3::parse_input("./sample.input").unwrap()
+    }
 }
@@ -0,0 +1,54 @@
+//! Strict validation throughput on a generated batch of a million passports.
+//!
+//! Run with `cargo bench --bench validators`.
+
+use day4::day4;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::time::Instant;
+
+const RECORDS: usize = 1_000_000;
+
+fn main() {
+    let path = env::temp_dir().join("day4_million.input");
+    write_batch(&path);
+    let size = fs::metadata(&path).unwrap().len();
+
+    let start = Instant::now();
+    let mut f = BufReader::new(File::open(&path).unwrap());
+    let valid = day4::count_strictly_valid_passports(&mut f);
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} records ({} MB), {} valid in {:.2?} ({:.0} records/s)",
+        RECORDS,
+        size / 1_000_000,
+        valid,
+        elapsed,
+        RECORDS as f64 / elapsed.as_secs_f64()
+    );
+
+    fs::remove_file(&path).unwrap();
+}
+
+/// Cycles through valid passports and ones breaking each rule.
+fn write_batch(path: &std::path::Path) {
+    let variants = [
+        "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147",
+        "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn\npid:087499704",
+        "byr:2003 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327",
+        "byr:1937 iyr:2017 eyr:2020 hgt:194cm hcl:#fffffd ecl:gry pid:860033327",
+        "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#123abcde ecl:gry pid:860033327",
+        "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:zzz pid:0123456789",
+        "iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327",
+    ];
+    let mut out = BufWriter::new(File::create(path).unwrap());
+    for i in 0..RECORDS {
+        if i > 0 {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "{}", variants[i % variants.len()]).unwrap();
+    }
+    out.flush().unwrap();
+}
@@ -0,0 +1,517 @@
+//! Passports as JSON Lines and CSV, read into the same [`Record`]s as the
+//! blank-line separated batch files so they validate the same way.
+//!
+//! A JSON Lines file holds one flat object per line, e.g.
+//! `{"byr":"1937","hgt":"183cm"}`. Numbers are kept as written and `null`
+//! values count as absent. A CSV file starts with a header row naming the
+//! fields; an empty cell means the field is absent.
+
+use super::Record;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    /// The input could not be understood. Lines are 1-based.
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Io(e) => write!(f, "cannot read passports: {}", e),
+            FormatError::Invalid { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> FormatError {
+        FormatError::Io(e)
+    }
+}
+
+/// Reads one passport per non-blank line of JSON.
+pub struct JsonLinesReader<R> {
+    lines: io::Lines<R>,
+    line_number: usize,
+}
+
+impl<R: BufRead> JsonLinesReader<R> {
+    pub fn new(input: R) -> JsonLinesReader<R> {
+        JsonLinesReader {
+            lines: input.lines(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonLinesReader<R> {
+    type Item = Result<Record, FormatError>;
+
+    fn next(&mut self) -> Option<Result<Record, FormatError>> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let n = self.line_number;
+            return Some(
+                parse_json_object(&line)
+                    .map(|fields| Record {
+                        fields,
+                        malformed: Vec::new(),
+                        lines: n..=n,
+                    })
+                    .map_err(|reason| FormatError::Invalid { line: n, reason }),
+            );
+        }
+    }
+}
+
+/// Writes each record as a JSON object on its own line, every value as a string.
+pub fn write_json_lines<'a, I>(out: &mut dyn Write, records: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    for record in records {
+        let members: Vec<String> = record
+            .fields
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+            .collect();
+        writeln!(out, "{{{}}}", members.join(","))?;
+    }
+    Ok(())
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Parses a flat JSON object whose values are strings, numbers, booleans or null.
+fn parse_json_object(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut parser = JsonParser {
+        chars: text.chars().collect(),
+        position: 0,
+    };
+    let mut fields = Vec::new();
+
+    parser.expect('{')?;
+    if !parser.consume('}') {
+        loop {
+            let key = parser.string()?;
+            parser.expect(':')?;
+            if let Some(value) = parser.value()? {
+                fields.push((key, value));
+            }
+            if parser.consume('}') {
+                break;
+            }
+            parser.expect(',')?;
+        }
+    }
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(String::from("unexpected text after the object"));
+    }
+    Ok(fields)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonParser {
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next_char(&mut self) -> Result<char, String> {
+        let c = self
+            .peek()
+            .ok_or_else(|| String::from("unexpected end of line"))?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn consume(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.consume(expected) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(c) => Err(format!(
+                "expected '{}' at column {}, found '{}'",
+                expected,
+                self.position + 1,
+                c
+            )),
+            None => Err(format!("expected '{}', found end of line", expected)),
+        }
+    }
+
+    /// A scalar value as text, or `None` for null.
+    fn value(&mut self) -> Result<Option<String>, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string().map(Some),
+            Some('{') | Some('[') => Err(format!(
+                "nested value at column {}, only flat objects are supported",
+                self.position + 1
+            )),
+            Some(_) => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                {
+                    self.position += 1;
+                }
+                let literal: String = self.chars[start..self.position].iter().collect();
+                match literal.as_str() {
+                    "null" => Ok(None),
+                    "true" | "false" => Ok(Some(literal)),
+                    _ if is_json_number(&literal) => Ok(Some(literal)),
+                    _ => Err(format!("invalid value at column {}", start + 1)),
+                }
+            }
+            None => Err(String::from("expected a value, found end of line")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next_char()? {
+                '"' => return Ok(out),
+                '\\' => match self.next_char()? {
+                    '"' => out.push('"'),
+                    '\\' => out.push('\\'),
+                    '/' => out.push('/'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'u' => out.push(self.unicode_escape()?),
+                    c => return Err(format!("invalid escape \\{}", c)),
+                },
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| String::from("invalid unicode escape"));
+        }
+        // A surrogate pair spread over two escapes.
+        if self.next_char()? != '\\' || self.next_char()? != 'u' {
+            return Err(String::from("unpaired surrogate in unicode escape"));
+        }
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(String::from("invalid surrogate pair in unicode escape"));
+        }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+            .ok_or_else(|| String::from("invalid surrogate pair in unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next_char()?
+                .to_digit(16)
+                .ok_or_else(|| String::from("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+}
+
+/// Whether a literal follows the JSON number grammar:
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+fn is_json_number(literal: &str) -> bool {
+    fn digits(text: &str) -> usize {
+        text.bytes().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let mut rest = literal.strip_prefix('-').unwrap_or(literal);
+    match digits(rest) {
+        0 => return false,
+        n if n > 1 && rest.starts_with('0') => return false,
+        n => rest = &rest[n..],
+    }
+    if let Some(fraction) = rest.strip_prefix('.') {
+        match digits(fraction) {
+            0 => return false,
+            n => rest = &fraction[n..],
+        }
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        match digits(exponent) {
+            0 => return false,
+            n => rest = &exponent[n..],
+        }
+    }
+    rest.is_empty()
+}
+
+/// Reads one passport per CSV row, after a header row naming the fields.
+/// Quoted cells may contain commas, quotes (doubled) and line breaks.
+pub struct CsvReader<R> {
+    lines: io::Lines<R>,
+    line_number: usize,
+    header: Option<Vec<String>>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(input: R) -> CsvReader<R> {
+        CsvReader {
+            lines: input.lines(),
+            line_number: 0,
+            header: None,
+        }
+    }
+
+    /// The cells of the next non-blank row and the line it started on.
+    fn row(&mut self) -> Option<Result<(Vec<String>, usize), FormatError>> {
+        let mut text = String::new();
+        let mut start = 0;
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None if text.is_empty() => return None,
+                None => {
+                    return Some(Err(FormatError::Invalid {
+                        line: start,
+                        reason: String::from("unterminated quoted cell"),
+                    }))
+                }
+            };
+            self.line_number += 1;
+            if text.is_empty() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                start = self.line_number;
+            } else {
+                text.push('\n');
+            }
+            text.push_str(&line);
+
+            if let Some(cells) = split_csv_row(&text) {
+                return Some(Ok((cells, start)));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = Result<Record, FormatError>;
+
+    fn next(&mut self) -> Option<Result<Record, FormatError>> {
+        if self.header.is_none() {
+            match self.row()? {
+                Ok((header, start)) => {
+                    if let Some(column) = header.iter().position(|name| name.is_empty()) {
+                        return Some(Err(FormatError::Invalid {
+                            line: start,
+                            reason: format!("column {} has no name", column + 1),
+                        }));
+                    }
+                    self.header = Some(header)
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        let (cells, start) = match self.row()? {
+            Ok(row) => row,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let header = self.header.as_ref().unwrap();
+        if cells.len() != header.len() {
+            return Some(Err(FormatError::Invalid {
+                line: start,
+                reason: format!("{} cells but {} columns", cells.len(), header.len()),
+            }));
+        }
+        let fields = header
+            .iter()
+            .zip(cells)
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.clone(), value))
+            .collect();
+
+        Some(Ok(Record {
+            fields,
+            malformed: Vec::new(),
+            lines: start..=self.line_number,
+        }))
+    }
+}
+
+/// Splits a row into cells, or `None` if a quoted cell continues on the next line.
+fn split_csv_row(text: &str) -> Option<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => cells.push(std::mem::take(&mut cell)),
+            (c, _) => cell.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    cells.push(cell);
+    Some(cells)
+}
+
+/// Writes a header row of `columns` and one row per record. Fields not
+/// listed in `columns` are left out, and absent fields are empty cells.
+pub fn write_csv<'a, I>(out: &mut dyn Write, columns: &[&str], records: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let header: Vec<String> = columns.iter().map(|c| csv_cell(c)).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for record in records {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                record
+                    .fields
+                    .iter()
+                    .find(|f| f.0 == *column)
+                    .map_or_else(String::new, |f| csv_cell(&f.1))
+            })
+            .collect();
+        writeln!(out, "{}", cells.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    String::from(value)
+}
+
+/// Writes records in the blank-line separated `key:value` batch format,
+/// malformed tokens after the fields. The format has no escapes, so a record
+/// with neither fields nor malformed tokens, a field whose key is empty or
+/// holds whitespace or `:`, a value holding whitespace, or a malformed token
+/// which would read back as a field, is refused with
+/// [`io::ErrorKind::InvalidInput`] rather than written so that it reads
+/// back differently.
+pub fn write_batch<'a, I>(out: &mut dyn Write, records: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    for (i, record) in records.into_iter().enumerate() {
+        if record.fields.is_empty() && record.malformed.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot write an empty record as a batch passport",
+            ));
+        }
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let mut pairs: Vec<String> = record
+            .fields
+            .iter()
+            .map(|(key, value)| batch_pair(key, value))
+            .collect::<io::Result<_>>()?;
+        for token in &record.malformed {
+            pairs.push(batch_token(token)?);
+        }
+        writeln!(out, "{}", pairs.join(" "))?;
+    }
+    Ok(())
+}
+
+fn batch_pair(key: &str, value: &str) -> io::Result<String> {
+    // The reader splits at the first `:`, so only the key must be free of them.
+    let unsafe_key = key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == ':');
+    if unsafe_key || value.contains(char::is_whitespace) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "cannot write {:?}:{:?} as a batch key:value pair",
+                key, value
+            ),
+        ));
+    }
+    Ok(format!("{}:{}", key, value))
+}
+
+fn batch_token(token: &str) -> io::Result<String> {
+    let reads_back = !token.is_empty()
+        && !token.contains(char::is_whitespace)
+        && token.find(':').is_none_or(|i| i == 0);
+    if !reads_back {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot write {:?} as a malformed batch token", token),
+        ));
+    }
+    Ok(String::from(token))
+}
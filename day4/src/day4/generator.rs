@@ -0,0 +1,368 @@
+//! Seeded batches of synthetic passports for testing validators. Each
+//! passport is built from the schema's rules and remembers how it was built,
+//! so the outcome validation should reach is known up front.
+
+use super::formats;
+use super::schema::{FieldRule, FieldType, Schema};
+use super::Record;
+use std::fmt;
+use std::io::{self, Write};
+
+/// How a generated passport relates to the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Every field present and valid.
+    Valid,
+    /// One required field left out.
+    MissingField,
+    /// One bounded field set to a bound, or just past it.
+    Boundary,
+    /// One field holding a value of the wrong shape, or written as a token
+    /// which is not a `key:value` pair.
+    Malformed,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::Valid => "valid",
+            Kind::MissingField => "missing",
+            Kind::Boundary => "boundary",
+            Kind::Malformed => "malformed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Relative weights of each kind of passport in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mix {
+    pub valid: u32,
+    pub missing: u32,
+    pub boundary: u32,
+    pub malformed: u32,
+}
+
+impl Default for Mix {
+    fn default() -> Mix {
+        Mix {
+            valid: 4,
+            missing: 2,
+            boundary: 2,
+            malformed: 2,
+        }
+    }
+}
+
+impl Mix {
+    fn weights(&self) -> [(Kind, u32); 4] {
+        [
+            (Kind::Valid, self.valid),
+            (Kind::MissingField, self.missing),
+            (Kind::Boundary, self.boundary),
+            (Kind::Malformed, self.malformed),
+        ]
+    }
+}
+
+/// A generated passport and the outcome validation should reach.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    pub record: Record,
+    pub kind: Kind,
+    /// The field that was left out or altered, if any.
+    pub field: Option<String>,
+    /// Whether every required field is present.
+    pub expected_valid: bool,
+    /// Whether the passport passes the schema's built-in rules. Custom
+    /// validators registered on the schema are not taken into account.
+    pub expected_strictly_valid: bool,
+}
+
+/// A batch of generated passports, one per line of the batch format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    pub passports: Vec<Generated>,
+}
+
+impl Batch {
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.passports.iter().map(|p| &p.record)
+    }
+
+    pub fn expected_valid_count(&self) -> usize {
+        self.passports.iter().filter(|p| p.expected_valid).count()
+    }
+
+    pub fn expected_strictly_valid_count(&self) -> usize {
+        self.passports
+            .iter()
+            .filter(|p| p.expected_strictly_valid)
+            .count()
+    }
+
+    /// Writes the passports in the day4 batch format.
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        formats::write_batch(out, self.records())
+    }
+
+    /// Writes one line per passport with its kind, the field it concerns
+    /// and the expected outcomes:
+    ///
+    /// ```text
+    /// # passport kind field valid strictly-valid
+    /// 1 valid - true true
+    /// 2 boundary hgt true false
+    /// ```
+    pub fn write_expected(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "# passport kind field valid strictly-valid")?;
+        for (i, passport) in self.passports.iter().enumerate() {
+            writeln!(
+                out,
+                "{} {} {} {} {}",
+                i + 1,
+                passport.kind,
+                passport.field.as_deref().unwrap_or("-"),
+                passport.expected_valid,
+                passport.expected_strictly_valid
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds passports for a schema from a seed; the same seed, schema and mix
+/// always produce the same batch.
+#[derive(Debug, Clone)]
+pub struct Generator<'a> {
+    schema: &'a Schema,
+    mix: Mix,
+    rng: Rng,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(schema: &'a Schema, seed: u64) -> Generator<'a> {
+        Generator {
+            schema,
+            mix: Mix::default(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn with_mix(mut self, mix: Mix) -> Generator<'a> {
+        self.mix = mix;
+        self
+    }
+
+    /// Generates `count` passports in the proportions of the mix, shuffled.
+    /// A kind the schema cannot produce, such as a boundary passport for a
+    /// schema without bounded fields, is generated as a valid passport.
+    pub fn batch(&mut self, count: usize) -> Batch {
+        let mut kinds = self.quotas(count);
+        for i in (1..kinds.len()).rev() {
+            let j = self.rng.below(i as u64 + 1) as usize;
+            kinds.swap(i, j);
+        }
+        Batch {
+            passports: kinds
+                .into_iter()
+                .enumerate()
+                .map(|(i, kind)| self.passport(kind, 2 * i + 1))
+                .collect(),
+        }
+    }
+
+    /// The kind of each passport, with exactly the mix's share of each kind
+    /// up to rounding. Leftovers go to the kinds listed first.
+    fn quotas(&self, count: usize) -> Vec<Kind> {
+        let weights = self.mix.weights();
+        let total: u64 = weights.iter().map(|w| u64::from(w.1)).sum();
+        if total == 0 {
+            return vec![Kind::Valid; count];
+        }
+        let mut kinds: Vec<Kind> = Vec::with_capacity(count);
+        for (kind, weight) in weights.iter() {
+            let share = (count as u64 * u64::from(*weight) / total) as usize;
+            kinds.extend(std::iter::repeat_n(*kind, share));
+        }
+        let mut leftovers = weights.iter().filter(|w| w.1 > 0).cycle();
+        while kinds.len() < count {
+            kinds.push(leftovers.next().unwrap().0);
+        }
+        kinds
+    }
+
+    fn passport(&mut self, kind: Kind, line: usize) -> Generated {
+        let rules = self.schema.fields();
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut malformed: Vec<String> = Vec::new();
+        for rule in rules {
+            if rule.required || self.rng.below(2) == 0 {
+                let value = self.valid_value(&rule.field_type);
+                fields.push((rule.name.clone(), value));
+            }
+        }
+
+        let target = match kind {
+            Kind::Valid => None,
+            Kind::MissingField => self.pick(rules, |r| r.required),
+            Kind::Boundary => self.pick(rules, |r| is_bounded(&r.field_type)),
+            Kind::Malformed => self.pick(rules, |r| r.field_type != FieldType::Any),
+        };
+        let (kind, field, expected_valid, expected_strictly_valid) = match target {
+            None => (Kind::Valid, None, true, true),
+            Some(rule) if kind == Kind::MissingField => {
+                fields.retain(|f| f.0 != rule.name);
+                (kind, Some(rule.name.clone()), false, false)
+            }
+            Some(rule) if kind == Kind::Malformed && self.rng.below(2) == 0 => {
+                // The pair loses its colon, so the field is gone and the
+                // malformed token rejects the passport.
+                let value = match fields.iter().position(|f| f.0 == rule.name) {
+                    Some(i) => fields.remove(i).1,
+                    None => self.valid_value(&rule.field_type),
+                };
+                malformed.push(format!("{}{}", rule.name, value));
+                (kind, Some(rule.name.clone()), !rule.required, false)
+            }
+            Some(rule) => {
+                let (value, valid) = if kind == Kind::Boundary {
+                    self.boundary_value(&rule.field_type)
+                } else {
+                    (self.malformed_value(&rule.field_type), false)
+                };
+                match fields.iter_mut().find(|f| f.0 == rule.name) {
+                    Some(f) => f.1 = value,
+                    None => fields.push((rule.name.clone(), value)),
+                }
+                (kind, Some(rule.name.clone()), true, valid)
+            }
+        };
+
+        for i in (1..fields.len()).rev() {
+            let j = self.rng.below(i as u64 + 1) as usize;
+            fields.swap(i, j);
+        }
+        Generated {
+            record: Record {
+                fields,
+                malformed,
+                lines: line..=line,
+            },
+            kind,
+            field,
+            expected_valid,
+            expected_strictly_valid,
+        }
+    }
+
+    fn pick<'r, F>(&mut self, rules: &'r [FieldRule], eligible: F) -> Option<&'r FieldRule>
+    where
+        F: Fn(&FieldRule) -> bool,
+    {
+        let candidates: Vec<&FieldRule> = rules.iter().filter(|r| eligible(r)).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[self.rng.below(candidates.len() as u64) as usize])
+    }
+
+    /// A value the type accepts.
+    fn valid_value(&mut self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Year { min, max } => self.between(*min, *max).to_string(),
+            FieldType::Measure(units) => {
+                let unit = &units[self.rng.below(units.len() as u64) as usize];
+                let value = self.between(unit.min, unit.max);
+                format!("{}{}", value, unit.name)
+            }
+            FieldType::HexColour => format!("#{:06x}", self.rng.below(0x100_0000)),
+            FieldType::Enum(values) => values[self.rng.below(values.len() as u64) as usize].clone(),
+            FieldType::Digits(count) => self.digits(*count),
+            FieldType::Any => self.between(100, 350).to_string(),
+        }
+    }
+
+    /// A value at one of the type's bounds, or one past it, and whether it
+    /// is valid.
+    fn boundary_value(&mut self, field_type: &FieldType) -> (String, bool) {
+        let (unit, min, max) = match field_type {
+            FieldType::Year { min, max } => ("", *min, *max),
+            FieldType::Measure(units) => {
+                let unit = &units[self.rng.below(units.len() as u64) as usize];
+                (unit.name.as_str(), unit.min, unit.max)
+            }
+            _ => unreachable!("only bounded fields are picked"),
+        };
+        // Past a bound at the end of the i32 range, the bound itself is used.
+        let (value, valid) = match self.rng.below(4) {
+            0 => (min, true),
+            1 => (max, true),
+            2 => min.checked_sub(1).map_or((min, true), |v| (v, false)),
+            _ => max.checked_add(1).map_or((max, true), |v| (v, false)),
+        };
+        (format!("{}{}", value, unit), valid)
+    }
+
+    fn malformed_value(&mut self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Year { .. } => self.between(100, 999).to_string(),
+            FieldType::Measure(units) => {
+                let unit = &units[self.rng.below(units.len() as u64) as usize];
+                self.between(unit.min, unit.max).to_string()
+            }
+            FieldType::HexColour => format!("{:06x}", self.rng.below(0x100_0000)),
+            FieldType::Enum(values) => {
+                let mut value = values[self.rng.below(values.len() as u64) as usize].clone();
+                while values.contains(&value) {
+                    value.push('x');
+                }
+                value
+            }
+            FieldType::Digits(count) => self.digits(count + 1),
+            FieldType::Any => unreachable!("any value is well formed"),
+        }
+    }
+
+    fn between(&mut self, min: i32, max: i32) -> i32 {
+        let span = (i64::from(max) - i64::from(min) + 1) as u64;
+        (i64::from(min) + self.rng.below(span) as i64) as i32
+    }
+
+    fn digits(&mut self, count: usize) -> String {
+        (0..count)
+            .map(|_| char::from(b'0' + self.rng.below(10) as u8))
+            .collect()
+    }
+}
+
+fn is_bounded(field_type: &FieldType) -> bool {
+    matches!(field_type, FieldType::Year { .. } | FieldType::Measure(_))
+}
+
+/// SplitMix64; good enough to vary test data, and stable across platforms
+/// and releases, unlike the standard library's hashers.
+#[derive(Debug, Clone)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`. The slight bias of the modulo does not matter
+    /// for test data.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
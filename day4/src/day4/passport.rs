@@ -0,0 +1,302 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A passport with every field converted to its own type. Conversion only
+/// checks that values are well formed; year and height bounds are left to
+/// the [`Schema`](super::schema::Schema).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passport {
+    pub birth_year: u16,
+    pub issue_year: u16,
+    pub expiration_year: u16,
+    pub height: Height,
+    pub hair_color: HairColor,
+    pub eye_color: EyeColor,
+    pub passport_id: PassportId,
+    pub country_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Height {
+    Cm(u32),
+    In(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HairColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EyeColor {
+    Amb,
+    Blu,
+    Brn,
+    Gry,
+    Grn,
+    Hzl,
+    Oth,
+}
+
+/// Nine digits, leading zeros included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassportId(String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassportError {
+    Missing(String),
+    Invalid {
+        field: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for PassportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassportError::Missing(field) => write!(f, "{} is missing", field),
+            PassportError::Invalid {
+                field,
+                value,
+                expected,
+            } => write!(f, "{} {} is not {}", field, value, expected),
+        }
+    }
+}
+
+impl std::error::Error for PassportError {}
+
+impl TryFrom<&[(String, String)]> for Passport {
+    type Error = Vec<PassportError>;
+
+    /// Converts a `key:value` record, collecting every missing or malformed
+    /// field. The first occurrence of a repeated key is used and unknown keys
+    /// are ignored.
+    fn try_from(record: &[(String, String)]) -> Result<Passport, Vec<PassportError>> {
+        let mut errors = Vec::new();
+        let birth_year = convert(record, "byr", parse_year, &mut errors);
+        let issue_year = convert(record, "iyr", parse_year, &mut errors);
+        let expiration_year = convert(record, "eyr", parse_year, &mut errors);
+        let height = convert(record, "hgt", |v| v.parse().ok(), &mut errors);
+        let hair_color = convert(record, "hcl", |v| v.parse().ok(), &mut errors);
+        let eye_color = convert(record, "ecl", |v| v.parse().ok(), &mut errors);
+        let passport_id = convert(record, "pid", |v| v.parse().ok(), &mut errors);
+        let country_id = value_of(record, "cid").map(String::from);
+
+        match (
+            birth_year,
+            issue_year,
+            expiration_year,
+            height,
+            hair_color,
+            eye_color,
+            passport_id,
+        ) {
+            (Some(byr), Some(iyr), Some(eyr), Some(hgt), Some(hcl), Some(ecl), Some(pid))
+                if errors.is_empty() =>
+            {
+                Ok(Passport {
+                    birth_year: byr,
+                    issue_year: iyr,
+                    expiration_year: eyr,
+                    height: hgt,
+                    hair_color: hcl,
+                    eye_color: ecl,
+                    passport_id: pid,
+                    country_id,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+impl Passport {
+    /// The passport as a `key:value` record, always in the order byr, iyr,
+    /// eyr, hgt, hcl, ecl, pid, cid whatever order the input had. Years and
+    /// heights are written from their numbers, so leading zeros in the input,
+    /// e.g. `hgt:0183cm`, do not survive the round trip.
+    pub fn to_record(&self) -> Vec<(String, String)> {
+        let mut record = vec![
+            (String::from("byr"), self.birth_year.to_string()),
+            (String::from("iyr"), self.issue_year.to_string()),
+            (String::from("eyr"), self.expiration_year.to_string()),
+            (String::from("hgt"), self.height.to_string()),
+            (String::from("hcl"), self.hair_color.to_string()),
+            (String::from("ecl"), self.eye_color.to_string()),
+            (String::from("pid"), self.passport_id.to_string()),
+        ];
+        if let Some(cid) = &self.country_id {
+            record.push((String::from("cid"), cid.clone()));
+        }
+        record
+    }
+}
+
+impl fmt::Display for Passport {
+    /// Writes the passport on one line, the way the batch files list fields.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<String> = self
+            .to_record()
+            .iter()
+            .map(|(key, value)| format!("{}:{}", key, value))
+            .collect();
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+/// What each expected value looks like, used in conversion errors.
+fn expectation(field: &str) -> &'static str {
+    match field {
+        "byr" | "iyr" | "eyr" => "a four digit year",
+        "hgt" => "a height in cm or in",
+        "hcl" => "a colour like #a1b2c3",
+        "ecl" => "one of amb blu brn gry grn hzl oth",
+        "pid" => "a nine digit number",
+        _ => "valid",
+    }
+}
+
+fn value_of<'a>(record: &'a [(String, String)], field: &str) -> Option<&'a str> {
+    record.iter().find(|f| f.0 == field).map(|f| f.1.as_str())
+}
+
+fn convert<T, F>(
+    record: &[(String, String)],
+    field: &str,
+    parse: F,
+    errors: &mut Vec<PassportError>,
+) -> Option<T>
+where
+    F: Fn(&str) -> Option<T>,
+{
+    let value = match value_of(record, field) {
+        Some(value) => value,
+        None => {
+            errors.push(PassportError::Missing(String::from(field)));
+            return None;
+        }
+    };
+    let converted = parse(value);
+    if converted.is_none() {
+        errors.push(PassportError::Invalid {
+            field: String::from(field),
+            value: String::from(value),
+            expected: expectation(field),
+        });
+    }
+    converted
+}
+
+fn is_digits(value: &str, count: usize) -> bool {
+    value.len() == count && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_year(value: &str) -> Option<u16> {
+    if !is_digits(value, 4) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+impl FromStr for Height {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Height, ()> {
+        let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+        let number = digits.parse().map_err(|_| ())?;
+        match &value[digits.len()..] {
+            "cm" => Ok(Height::Cm(number)),
+            "in" => Ok(Height::In(number)),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Height::Cm(v) => write!(f, "{}cm", v),
+            Height::In(v) => write!(f, "{}in", v),
+        }
+    }
+}
+
+impl FromStr for HairColor {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<HairColor, ()> {
+        let hex = value.strip_prefix('#').ok_or(())?;
+        if hex.len() != 6 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return Err(());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ());
+        Ok(HairColor {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl fmt::Display for HairColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for EyeColor {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<EyeColor, ()> {
+        match value {
+            "amb" => Ok(EyeColor::Amb),
+            "blu" => Ok(EyeColor::Blu),
+            "brn" => Ok(EyeColor::Brn),
+            "gry" => Ok(EyeColor::Gry),
+            "grn" => Ok(EyeColor::Grn),
+            "hzl" => Ok(EyeColor::Hzl),
+            "oth" => Ok(EyeColor::Oth),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = match self {
+            EyeColor::Amb => "amb",
+            EyeColor::Blu => "blu",
+            EyeColor::Brn => "brn",
+            EyeColor::Gry => "gry",
+            EyeColor::Grn => "grn",
+            EyeColor::Hzl => "hzl",
+            EyeColor::Oth => "oth",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+impl FromStr for PassportId {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<PassportId, ()> {
+        if !is_digits(value, 9) {
+            return Err(());
+        }
+        Ok(PassportId(String::from(value)))
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
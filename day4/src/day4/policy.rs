@@ -0,0 +1,149 @@
+use super::schema::Schema;
+use super::Record;
+use std::fmt;
+
+/// Which value counts when a passport lists the same key more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// The passport is rejected; the first value is still validated.
+    Reject,
+    FirstWins,
+    LastWins,
+}
+
+/// What to do about keys missing from the schema, or tokens that are not
+/// `key:value` pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Ignore,
+    /// Reported without rejecting the passport.
+    Warn,
+    Reject,
+}
+
+/// How forgiving to be about the shape of a passport record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldPolicy {
+    pub duplicates: DuplicatePolicy,
+    pub unknown: Action,
+    pub malformed: Action,
+}
+
+impl Default for FieldPolicy {
+    /// First occurrence wins and unknown keys are ignored, as in the puzzle,
+    /// but malformed tokens reject the passport.
+    fn default() -> FieldPolicy {
+        FieldPolicy {
+            duplicates: DuplicatePolicy::FirstWins,
+            unknown: Action::Ignore,
+            malformed: Action::Reject,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    Duplicate { field: String, values: Vec<String> },
+    Unknown { field: String },
+    Malformed { token: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    /// Whether the violation rejects the passport, or is only a warning.
+    pub rejects: bool,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.rejects {
+            write!(f, "warning: ")?;
+        }
+        match &self.kind {
+            ViolationKind::Duplicate { field, values } if values.is_empty() => {
+                write!(f, "{} repeated", field)
+            }
+            ViolationKind::Duplicate { field, values } => {
+                write!(f, "{} repeated ({})", field, values.join(", "))
+            }
+            ViolationKind::Unknown { field } => write!(f, "unknown field {}", field),
+            ViolationKind::Malformed { token } => write!(f, "malformed token {:?}", token),
+        }
+    }
+}
+
+impl FieldPolicy {
+    /// Picks one value per key and lists what the policy objects to.
+    pub fn apply(
+        &self,
+        record: &Record,
+        schema: &Schema,
+    ) -> (Vec<(String, String)>, Vec<Violation>) {
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut violations = Vec::new();
+
+        for token in &record.malformed {
+            report(
+                &mut violations,
+                self.malformed,
+                ViolationKind::Malformed {
+                    token: token.clone(),
+                },
+            );
+        }
+
+        for (key, value) in &record.fields {
+            if let Some(known) = fields.iter_mut().find(|f| &f.0 == key) {
+                if self.duplicates == DuplicatePolicy::LastWins {
+                    known.1 = value.clone();
+                }
+                continue;
+            }
+            if schema.field(key).is_none() {
+                report(
+                    &mut violations,
+                    self.unknown,
+                    ViolationKind::Unknown { field: key.clone() },
+                );
+            }
+            fields.push((key.clone(), value.clone()));
+        }
+
+        if self.duplicates == DuplicatePolicy::Reject {
+            for (key, _) in &fields {
+                let values: Vec<String> = record
+                    .fields
+                    .iter()
+                    .filter(|f| &f.0 == key)
+                    .map(|f| f.1.clone())
+                    .collect();
+                if values.len() > 1 {
+                    violations.push(Violation {
+                        kind: ViolationKind::Duplicate {
+                            field: key.clone(),
+                            values,
+                        },
+                        rejects: true,
+                    });
+                }
+            }
+        }
+
+        (fields, violations)
+    }
+}
+
+fn report(violations: &mut Vec<Violation>, action: Action, kind: ViolationKind) {
+    match action {
+        Action::Ignore => {}
+        Action::Warn => violations.push(Violation {
+            kind,
+            rejects: false,
+        }),
+        Action::Reject => violations.push(Violation {
+            kind,
+            rejects: true,
+        }),
+    }
+}
@@ -0,0 +1,66 @@
+use super::Record;
+use std::io::{self, BufRead};
+
+/// Reads passports one at a time from a batch file, so batches of any size
+/// can be validated without loading them into memory. Passports are
+/// separated by lines holding nothing but whitespace, and fields by any
+/// whitespace.
+pub struct PassportReader<R> {
+    lines: io::Lines<R>,
+    line_number: usize,
+}
+
+impl<R: BufRead> PassportReader<R> {
+    pub fn new(input: R) -> PassportReader<R> {
+        PassportReader {
+            lines: input.lines(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PassportReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<io::Result<Record>> {
+        let mut record: Option<Record> = None;
+
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return record.map(Ok),
+            };
+            self.line_number += 1;
+
+            if line.trim().is_empty() {
+                if record.is_some() {
+                    return record.map(Ok);
+                }
+                continue;
+            }
+
+            let n = self.line_number;
+            let current = record.get_or_insert_with(|| Record {
+                fields: Vec::new(),
+                malformed: Vec::new(),
+                lines: n..=n,
+            });
+            current.lines = *current.lines.start()..=n;
+            for token in line.split_whitespace() {
+                match parse_pair(token) {
+                    Some(pair) => current.fields.push(pair),
+                    None => current.malformed.push(String::from(token)),
+                }
+            }
+        }
+    }
+}
+
+fn parse_pair(pair: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = pair.splitn(2, ':').collect();
+    match parts[..] {
+        [key, value] if !key.is_empty() => Some((String::from(key), String::from(value))),
+        _ => None,
+    }
+}
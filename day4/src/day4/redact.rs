@@ -0,0 +1,216 @@
+//! Masks identifying values before passports are printed, written out or
+//! reported, so rejected passports can be logged without leaking who they
+//! belong to.
+
+use super::passport::{Passport, PassportError};
+use super::policy::{Violation, ViolationKind};
+use super::repair::Suggestion;
+use super::report::{FieldFailure, PassportReport, ValidationReport};
+use super::Record;
+
+/// What to do with the value of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    Keep,
+    /// Every character replaced by `*`.
+    Hide,
+    /// All but the first `n` characters replaced by `*`.
+    KeepFirst(usize),
+    /// All but the last `n` characters replaced by `*`.
+    KeepLast(usize),
+    /// The field is left out altogether.
+    Drop,
+}
+
+impl Mask {
+    /// The masked value, or `None` if the field is dropped.
+    pub fn apply(&self, value: &str) -> Option<String> {
+        let length = value.chars().count();
+        let kept = |keep: usize, from_start: bool| -> String {
+            value
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    let visible = if from_start {
+                        i < keep
+                    } else {
+                        i + keep >= length
+                    };
+                    if visible {
+                        c
+                    } else {
+                        '*'
+                    }
+                })
+                .collect()
+        };
+        match self {
+            Mask::Keep => Some(String::from(value)),
+            Mask::Hide => Some(kept(0, true)),
+            Mask::KeepFirst(n) => Some(kept(*n, true)),
+            Mask::KeepLast(n) => Some(kept(*n, false)),
+            Mask::Drop => None,
+        }
+    }
+}
+
+/// Masking rules per field. Fields without a rule are kept as they are,
+/// and tokens which are not `key:value` pairs are masked by `tokens`, since
+/// they may hold a value with a mistyped key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redactor {
+    fields: Vec<(String, Mask)>,
+    tokens: Mask,
+}
+
+impl Default for Redactor {
+    /// Hides `pid`, shows only the century of `byr` and drops `cid`.
+    fn default() -> Redactor {
+        Redactor::new()
+            .with_mask("pid", Mask::Hide)
+            .with_mask("byr", Mask::KeepFirst(2))
+            .with_mask("cid", Mask::Drop)
+    }
+}
+
+impl Redactor {
+    /// A redactor keeping every field and hiding malformed tokens.
+    pub fn new() -> Redactor {
+        Redactor {
+            fields: Vec::new(),
+            tokens: Mask::Hide,
+        }
+    }
+
+    /// Sets the mask of a field, replacing any earlier one.
+    pub fn with_mask(mut self, field: &str, mask: Mask) -> Redactor {
+        self.fields.retain(|f| f.0 != field);
+        self.fields.push((String::from(field), mask));
+        self
+    }
+
+    pub fn with_token_mask(mut self, mask: Mask) -> Redactor {
+        self.tokens = mask;
+        self
+    }
+
+    pub fn mask(&self, field: &str) -> Mask {
+        self.fields
+            .iter()
+            .find(|f| f.0 == field)
+            .map_or(Mask::Keep, |f| f.1)
+    }
+
+    /// The masked value of a field, or `None` if the field is dropped.
+    pub fn value(&self, field: &str, value: &str) -> Option<String> {
+        self.mask(field).apply(value)
+    }
+
+    pub fn fields(&self, fields: &[(String, String)]) -> Vec<(String, String)> {
+        fields
+            .iter()
+            .filter_map(|(field, value)| Some((field.clone(), self.value(field, value)?)))
+            .collect()
+    }
+
+    pub fn record(&self, record: &Record) -> Record {
+        Record {
+            fields: self.fields(&record.fields),
+            malformed: record
+                .malformed
+                .iter()
+                .filter_map(|token| self.tokens.apply(token))
+                .collect(),
+            lines: record.lines.clone(),
+        }
+    }
+
+    /// A typed passport as a masked `key:value` record, ready to print or
+    /// write with [`formats`](super::formats).
+    pub fn passport(&self, passport: &Passport) -> Vec<(String, String)> {
+        self.fields(&passport.to_record())
+    }
+
+    pub fn error(&self, error: &PassportError) -> PassportError {
+        match error {
+            PassportError::Missing(field) => PassportError::Missing(field.clone()),
+            PassportError::Invalid {
+                field,
+                value,
+                expected,
+            } => PassportError::Invalid {
+                field: field.clone(),
+                value: self.value(field, value).unwrap_or_default(),
+                expected,
+            },
+        }
+    }
+
+    /// A failure keeps its field and reason; the value of a dropped field is
+    /// left empty, which its `Display` omits. Reasons are kept as they are,
+    /// since validators name fields in them but never quote values.
+    pub fn failure(&self, failure: &FieldFailure) -> FieldFailure {
+        FieldFailure {
+            field: failure.field.clone(),
+            value: self
+                .value(&failure.field, &failure.value)
+                .unwrap_or_default(),
+            reason: failure.reason.clone(),
+        }
+    }
+
+    /// A repair suggestion with both values masked by its field, or `None`
+    /// if the field is dropped.
+    pub fn suggestion(&self, suggestion: &Suggestion) -> Option<Suggestion> {
+        Some(Suggestion {
+            field: suggestion.field.clone(),
+            original: self.value(&suggestion.field, &suggestion.original)?,
+            proposed: self.value(&suggestion.field, &suggestion.proposed)?,
+            confidence: suggestion.confidence,
+            reason: suggestion.reason.clone(),
+        })
+    }
+
+    pub fn violation(&self, violation: &Violation) -> Violation {
+        let kind = match &violation.kind {
+            ViolationKind::Duplicate { field, values } => ViolationKind::Duplicate {
+                field: field.clone(),
+                values: values.iter().filter_map(|v| self.value(field, v)).collect(),
+            },
+            ViolationKind::Unknown { field } => ViolationKind::Unknown {
+                field: field.clone(),
+            },
+            ViolationKind::Malformed { token } => ViolationKind::Malformed {
+                token: self.tokens.apply(token).unwrap_or_default(),
+            },
+        };
+        Violation {
+            kind,
+            rejects: violation.rejects,
+        }
+    }
+
+    pub fn passport_report(&self, report: &PassportReport) -> PassportReport {
+        PassportReport {
+            ordinal: report.ordinal,
+            lines: report.lines.clone(),
+            missing: report.missing.clone(),
+            invalid: report.invalid.iter().map(|f| self.failure(f)).collect(),
+            violations: report
+                .violations
+                .iter()
+                .map(|v| self.violation(v))
+                .collect(),
+        }
+    }
+
+    pub fn report(&self, report: &ValidationReport) -> ValidationReport {
+        ValidationReport {
+            passports: report
+                .passports
+                .iter()
+                .map(|p| self.passport_report(p))
+                .collect(),
+        }
+    }
+}
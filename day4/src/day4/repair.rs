@@ -0,0 +1,172 @@
+//! Proposed fixes for passports rejected over trivial mistakes. Nothing here
+//! changes a passport by itself: [`propose`] returns the corrected copy next
+//! to the original, and [`apply`] only applies the suggestions it is given.
+
+use super::schema::{FieldType, Schema};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        };
+        write!(f, "{}", level)
+    }
+}
+
+/// A corrected value for one invalid field. Both values are shown as they
+/// are; pass the suggestion through
+/// [`Redactor::suggestion`](super::redact::Redactor::suggestion) before
+/// printing it anywhere it may be logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub field: String,
+    pub original: String,
+    pub proposed: String,
+    pub confidence: Confidence,
+    pub reason: String,
+}
+
+impl fmt::Display for Suggestion {
+    /// `hcl 123abc -> #123abc (high confidence: added missing #)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} ({} confidence: {})",
+            self.field, self.original, self.proposed, self.confidence, self.reason
+        )
+    }
+}
+
+/// Suggestions for a passport together with the passport they would produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    pub suggestions: Vec<Suggestion>,
+    pub repaired: Vec<(String, String)>,
+    /// Whether the repaired passport passes strict validation.
+    pub valid_after_repair: bool,
+}
+
+/// Suggests a fix for every invalid field that has an obvious one. Each
+/// proposed value passes the field's checks, custom validators included.
+pub fn suggest(passport: &[(String, String)], schema: &Schema) -> Vec<Suggestion> {
+    passport
+        .iter()
+        .filter(|(field, value)| schema.check_field((field, value)).is_err())
+        .filter_map(|(field, value)| {
+            let rule = schema.field(field)?;
+            let (proposed, confidence, reason) = candidate(&rule.field_type, value)?;
+            if schema.check_field((field, &proposed)).is_err() {
+                return None;
+            }
+            Some(Suggestion {
+                field: field.clone(),
+                original: value.clone(),
+                proposed,
+                confidence,
+                reason: String::from(reason),
+            })
+        })
+        .collect()
+}
+
+/// Applies the suggestions to a copy of the passport and re-validates it.
+pub fn propose(passport: &[(String, String)], schema: &Schema) -> Proposal {
+    let suggestions = suggest(passport, schema);
+    let repaired = apply(passport, &suggestions);
+    Proposal {
+        valid_after_repair: schema.is_passport_strictly_valid(&repaired),
+        suggestions,
+        repaired,
+    }
+}
+
+/// A copy of the passport with the given suggestions applied to the fields
+/// still holding their original value.
+pub fn apply(passport: &[(String, String)], suggestions: &[Suggestion]) -> Vec<(String, String)> {
+    passport
+        .iter()
+        .map(|(field, value)| {
+            let fix = suggestions
+                .iter()
+                .find(|s| &s.field == field && &s.original == value);
+            match fix {
+                Some(s) => (field.clone(), s.proposed.clone()),
+                None => (field.clone(), value.clone()),
+            }
+        })
+        .collect()
+}
+
+fn candidate(field_type: &FieldType, value: &str) -> Option<(String, Confidence, &'static str)> {
+    let trimmed = value.trim();
+    if trimmed != value {
+        return Some((
+            String::from(trimmed),
+            Confidence::High,
+            "removed surrounding whitespace",
+        ));
+    }
+
+    match field_type {
+        FieldType::HexColour => {
+            let lower = value.to_ascii_lowercase();
+            let is_hex = |s: &str| s.len() == 6 && s.bytes().all(|b| b.is_ascii_hexdigit());
+            if is_hex(&lower) {
+                Some((format!("#{}", lower), Confidence::High, "added missing #"))
+            } else if lower != value {
+                Some((lower, Confidence::High, "lowercased hex digits"))
+            } else {
+                None
+            }
+        }
+        FieldType::Measure(units) => {
+            let number: i32 = value.parse().ok()?;
+            let mut fitting = units.iter().filter(|u| (u.min..=u.max).contains(&number));
+            match (fitting.next(), fitting.next()) {
+                (Some(unit), None) => Some((
+                    format!("{}{}", number, unit.name),
+                    Confidence::Medium,
+                    "added the only unit whose range fits",
+                )),
+                _ => None,
+            }
+        }
+        FieldType::Digits(count) => {
+            let digits = value.bytes().all(|b| b.is_ascii_digit());
+            if digits && value.len() == count + 1 && value.starts_with('0') {
+                Some((
+                    String::from(&value[1..]),
+                    Confidence::Medium,
+                    "dropped an extra leading zero",
+                ))
+            } else if digits && value.len() < *count {
+                Some((
+                    format!("{:0>width$}", value, width = count),
+                    Confidence::Low,
+                    "padded with leading zeros",
+                ))
+            } else {
+                None
+            }
+        }
+        FieldType::Enum(values) => {
+            let lower = value.to_ascii_lowercase();
+            if values.contains(&lower) {
+                Some((lower, Confidence::High, "lowercased"))
+            } else {
+                None
+            }
+        }
+        FieldType::Year { .. } | FieldType::Any => None,
+    }
+}
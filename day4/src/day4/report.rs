@@ -0,0 +1,230 @@
+use super::policy::{FieldPolicy, Violation};
+use super::schema::Schema;
+use super::Record;
+use std::fmt;
+use std::io;
+use std::ops::RangeInclusive;
+
+/// A field whose value breaks its rule. The reason never holds a value, so
+/// masking `value` by `field` is enough to redact a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFailure {
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+impl fmt::Display for FieldFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.value.is_empty() {
+            return write!(f, "{} {}", self.field, self.reason);
+        }
+        write!(f, "{} {} {}", self.field, self.value, self.reason)
+    }
+}
+
+/// Outcome of strictly validating one passport of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassportReport {
+    /// Position of the passport in the batch, starting at 1.
+    pub ordinal: usize,
+    pub lines: RangeInclusive<usize>,
+    pub missing: Vec<String>,
+    pub invalid: Vec<FieldFailure>,
+    /// Duplicate, unknown or malformed fields the policy objected to.
+    pub violations: Vec<Violation>,
+}
+
+impl PassportReport {
+    pub fn new(ordinal: usize, record: &Record, schema: &Schema) -> PassportReport {
+        PassportReport::with_policy(ordinal, record, schema, &FieldPolicy::default())
+    }
+
+    pub fn with_policy(
+        ordinal: usize,
+        record: &Record,
+        schema: &Schema,
+        policy: &FieldPolicy,
+    ) -> PassportReport {
+        let (fields, violations) = policy.apply(record, schema);
+        let missing = schema
+            .required_fields()
+            .filter(|rule| !fields.iter().any(|f| f.0 == rule.name))
+            .map(|rule| rule.name.clone())
+            .collect();
+        let mut invalid: Vec<FieldFailure> = fields
+            .iter()
+            .filter_map(|(field, value)| {
+                let reason = schema.check_field((field, value)).err()?;
+                Some(FieldFailure {
+                    field: field.clone(),
+                    value: value.clone(),
+                    reason,
+                })
+            })
+            .collect();
+        invalid.extend(schema.check_record(&fields));
+
+        PassportReport {
+            ordinal,
+            lines: record.lines.clone(),
+            missing,
+            invalid,
+            violations,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty()
+            && self.invalid.is_empty()
+            && self.violations.iter().all(|v| !v.rejects)
+    }
+}
+
+impl fmt::Display for PassportReport {
+    /// `passport 3 (lines 7-9): missing byr; hgt 194cm above max 193`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "passport {} (lines {}-{}): ",
+            self.ordinal,
+            self.lines.start(),
+            self.lines.end()
+        )?;
+        let mut problems: Vec<String> = Vec::new();
+        if self.is_valid() {
+            problems.push(String::from("valid"));
+        }
+        if !self.missing.is_empty() {
+            problems.push(format!("missing {}", self.missing.join(" ")));
+        }
+        problems.extend(self.invalid.iter().map(|failure| failure.to_string()));
+        problems.extend(
+            self.violations
+                .iter()
+                .map(|violation| violation.to_string()),
+        );
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
+/// How often a field made passports fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldStats {
+    pub field: String,
+    pub missing: usize,
+    pub invalid: usize,
+    /// Share of all passports where the field was missing or invalid.
+    pub failure_rate: f64,
+}
+
+/// Strict validation of a whole batch, passport by passport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub passports: Vec<PassportReport>,
+}
+
+impl ValidationReport {
+    pub fn new(records: &[Record], schema: &Schema) -> ValidationReport {
+        ValidationReport::with_policy(records, schema, &FieldPolicy::default())
+    }
+
+    pub fn with_policy(
+        records: &[Record],
+        schema: &Schema,
+        policy: &FieldPolicy,
+    ) -> ValidationReport {
+        ValidationReport {
+            passports: records
+                .iter()
+                .enumerate()
+                .map(|(i, record)| PassportReport::with_policy(i + 1, record, schema, policy))
+                .collect(),
+        }
+    }
+
+    /// Validates records as they are read, keeping only their reports;
+    /// stops at the first read error.
+    pub fn read<I>(
+        records: I,
+        schema: &Schema,
+        policy: &FieldPolicy,
+    ) -> io::Result<ValidationReport>
+    where
+        I: IntoIterator<Item = io::Result<Record>>,
+    {
+        records.into_iter().enumerate().try_fold(
+            ValidationReport {
+                passports: Vec::new(),
+            },
+            |mut report, (i, record)| {
+                let passport = PassportReport::with_policy(i + 1, &record?, schema, policy);
+                report.passports.push(passport);
+                Ok(report)
+            },
+        )
+    }
+
+    pub fn valid_count(&self) -> usize {
+        self.passports.iter().filter(|p| p.is_valid()).count()
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = &PassportReport> {
+        self.passports.iter().filter(|p| !p.is_valid())
+    }
+
+    /// Failures per field of the schema, in schema order. A passport counts
+    /// once per field even if it repeats an invalid field.
+    pub fn field_stats(&self, schema: &Schema) -> Vec<FieldStats> {
+        let total = self.passports.len();
+        schema
+            .fields()
+            .iter()
+            .map(|rule| {
+                let missing = self
+                    .passports
+                    .iter()
+                    .filter(|p| p.missing.contains(&rule.name))
+                    .count();
+                let invalid = self
+                    .passports
+                    .iter()
+                    .filter(|p| p.invalid.iter().any(|f| f.field == rule.name))
+                    .count();
+                FieldStats {
+                    field: rule.name.clone(),
+                    missing,
+                    invalid,
+                    failure_rate: if total == 0 {
+                        0.0
+                    } else {
+                        (missing + invalid) as f64 / total as f64
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Every rejected passport followed by the failure rate of each field.
+    pub fn summary(&self, schema: &Schema) -> String {
+        let mut out = String::new();
+        for passport in self.rejected() {
+            out.push_str(&format!("{}\n", passport));
+        }
+        out.push_str(&format!(
+            "{} of {} passports valid\n",
+            self.valid_count(),
+            self.passports.len()
+        ));
+        for stats in self.field_stats(schema) {
+            out.push_str(&format!(
+                "{} missing {} invalid {} failure rate {:.1}%\n",
+                stats.field,
+                stats.missing,
+                stats.invalid,
+                stats.failure_rate * 100.0
+            ));
+        }
+        out
+    }
+}
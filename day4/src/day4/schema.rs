@@ -0,0 +1,309 @@
+use super::policy::FieldPolicy;
+use super::report::{FieldFailure, PassportReport};
+use super::validators::{FieldValidator, RecordValidator, Validator, ValidatorSet};
+use super::Record;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What a field value has to look like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// A four digit year between `min` and `max`, both included.
+    Year { min: i32, max: i32 },
+    /// A number directly followed by one of the units, each unit with its own bounds.
+    Measure(Vec<Unit>),
+    /// `#` followed by six hex digits.
+    HexColour,
+    /// One of the listed values.
+    Enum(Vec<String>),
+    /// A number with exactly this many digits.
+    Digits(usize),
+    /// Anything goes.
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    pub name: String,
+    pub min: i32,
+    pub max: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRule {
+    pub name: String,
+    pub required: bool,
+    pub field_type: FieldType,
+}
+
+/// The set of passport fields an issuing authority expects, read from a text
+/// file with one field per line:
+///
+/// ```text
+/// # name  required|optional  type  bounds
+/// byr required year 1920-2002
+/// hgt required measure cm 150-193 in 59-76
+/// hcl required hex-colour
+/// ecl required enum amb blu brn gry grn hzl oth
+/// pid required digits 9
+/// cid optional any
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    fields: Vec<FieldRule>,
+    validators: ValidatorSet,
+}
+
+/// Schemas are equal when they list the same fields; custom validators are
+/// not compared.
+impl PartialEq for Schema {
+    fn eq(&self, other: &Schema) -> bool {
+        self.fields == other.fields
+    }
+}
+
+impl Eq for Schema {}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    /// A line of the schema could not be understood. Lines are 1-based.
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "cannot read schema: {}", e),
+            SchemaError::Invalid { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<io::Error> for SchemaError {
+    fn from(e: io::Error) -> SchemaError {
+        SchemaError::Io(e)
+    }
+}
+
+impl Default for Schema {
+    /// The rules of `default.schema`.
+    fn default() -> Schema {
+        Schema::parse(include_str!("../../default.schema")).unwrap()
+    }
+}
+
+impl Schema {
+    pub fn load<P>(schema_file: P) -> Result<Schema, SchemaError>
+    where
+        P: AsRef<Path>,
+    {
+        Schema::parse(&fs::read_to_string(schema_file)?)
+    }
+
+    /// Parses a schema, skipping blank lines and `#` comments.
+    pub fn parse(text: &str) -> Result<Schema, SchemaError> {
+        let mut fields: Vec<FieldRule> = Vec::new();
+        let mut validators: Vec<(String, Validator)> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: String| SchemaError::Invalid {
+                line: i + 1,
+                reason,
+            };
+
+            let rule = parse_rule(line).map_err(invalid)?;
+            if fields.iter().any(|f| f.name == rule.name) {
+                return Err(invalid(format!("field {} listed twice", rule.name)));
+            }
+            validators.push((
+                rule.name.clone(),
+                Validator::new(&rule.field_type).map_err(invalid)?,
+            ));
+            fields.push(rule);
+        }
+
+        Ok(Schema {
+            fields,
+            validators: ValidatorSet::from_validators(validators),
+        })
+    }
+
+    pub fn fields(&self) -> &[FieldRule] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    pub fn required_fields(&self) -> impl Iterator<Item = &FieldRule> {
+        self.fields.iter().filter(|f| f.required)
+    }
+
+    /// Whether every required field is present.
+    pub fn is_passport_valid(&self, passport: &[(String, String)]) -> bool {
+        self.required_fields()
+            .all(|rule| passport.iter().any(|f| f.0 == rule.name))
+    }
+
+    /// Whether a passport passes strict validation under the default
+    /// [`FieldPolicy`]: the first occurrence of every field counts.
+    pub fn is_passport_strictly_valid(&self, passport: &[(String, String)]) -> bool {
+        let record = Record {
+            fields: passport.to_vec(),
+            malformed: Vec::new(),
+            lines: 1..=1,
+        };
+        self.is_record_strictly_valid(&record, &FieldPolicy::default())
+    }
+
+    /// Whether a record passes strict validation, the same check a
+    /// [`PassportReport`] makes.
+    pub fn is_record_strictly_valid(&self, record: &Record, policy: &FieldPolicy) -> bool {
+        PassportReport::with_policy(0, record, self, policy).is_valid()
+    }
+
+    /// Adds a check run on `field` after its built-in rule.
+    pub fn register_field_validator<V>(&mut self, field: &str, validator: V)
+    where
+        V: FieldValidator + 'static,
+    {
+        self.validators.add_field_validator(field, validator);
+    }
+
+    /// Adds a check run across the fields of every passport.
+    pub fn register_record_validator<V>(&mut self, validator: V)
+    where
+        V: RecordValidator + 'static,
+    {
+        self.validators.add_record_validator(validator);
+    }
+
+    /// Fields missing from the schema are always valid.
+    pub fn is_field_valid(&self, field: (&str, &str)) -> bool {
+        self.check_field(field).is_ok()
+    }
+
+    /// Checks a field value with the validators compiled for this schema,
+    /// explaining what is wrong with it. Fields missing from the schema are
+    /// always valid.
+    pub fn check_field(&self, field: (&str, &str)) -> Result<(), String> {
+        self.validators.check(field)
+    }
+
+    /// Failures reported by the registered record validators.
+    pub fn check_record(&self, passport: &[(String, String)]) -> Vec<FieldFailure> {
+        self.validators.check_record(passport)
+    }
+}
+
+impl FieldType {
+    pub fn is_valid(&self, value: &str) -> bool {
+        self.check(value).is_ok()
+    }
+
+    /// Checks a value, explaining what is wrong with it, e.g. `above max 193`.
+    /// This compiles the type's pattern on every call; validating many values
+    /// is faster through [`Schema::check_field`].
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        Validator::new(self)?.check(value)
+    }
+}
+
+fn parse_rule(line: &str) -> Result<FieldRule, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 3 {
+        return Err(String::from(
+            "expected a name, required|optional and a type",
+        ));
+    }
+
+    let required = match tokens[1] {
+        "required" => true,
+        "optional" => false,
+        other => return Err(format!("expected required or optional, found {}", other)),
+    };
+
+    Ok(FieldRule {
+        name: String::from(tokens[0]),
+        required,
+        field_type: parse_type(tokens[2], &tokens[3..])?,
+    })
+}
+
+fn parse_type(name: &str, args: &[&str]) -> Result<FieldType, String> {
+    let field_type = match (name, args) {
+        ("year", [bounds]) => {
+            let (min, max) = parse_bounds(bounds)?;
+            if min < 1000 || max > 9999 {
+                return Err(format!(
+                    "year bounds must lie within 1000-9999, found {}",
+                    bounds
+                ));
+            }
+            FieldType::Year { min, max }
+        }
+        ("measure", units) if !units.is_empty() && units.len() % 2 == 0 => FieldType::Measure(
+            units
+                .chunks(2)
+                .map(|unit| {
+                    let (min, max) = parse_bounds(unit[1])?;
+                    Ok(Unit {
+                        name: String::from(unit[0]),
+                        min,
+                        max,
+                    })
+                })
+                .collect::<Result<Vec<Unit>, String>>()?,
+        ),
+        ("hex-colour", []) => FieldType::HexColour,
+        ("enum", values) if !values.is_empty() => {
+            FieldType::Enum(values.iter().map(|v| String::from(*v)).collect())
+        }
+        ("digits", [count]) => FieldType::Digits(
+            count
+                .parse()
+                .map_err(|_| format!("expected a digit count, found {}", count))?,
+        ),
+        ("any", []) => FieldType::Any,
+        ("year", _) => return Err(String::from("year expects bounds like 1920-2002")),
+        ("measure", _) => {
+            return Err(String::from(
+                "measure expects units with bounds like cm 150-193",
+            ))
+        }
+        ("enum", _) => return Err(String::from("enum expects at least one value")),
+        ("digits", _) => return Err(String::from("digits expects a digit count")),
+        ("hex-colour", _) | ("any", _) => return Err(format!("{} takes no bounds", name)),
+        _ => return Err(format!("unknown field type {}", name)),
+    };
+    Ok(field_type)
+}
+
+fn parse_bounds(bounds: &str) -> Result<(i32, i32), String> {
+    let invalid = || format!("expected bounds like 150-193, found {}", bounds);
+    // The separator is the first '-' after the lower bound's sign, so
+    // negative bounds read as e.g. -40--10.
+    let split = match bounds.get(1..).and_then(|rest| rest.find('-')) {
+        Some(i) => i + 1,
+        None => return Err(invalid()),
+    };
+    let min = bounds[..split].parse().map_err(|_| invalid())?;
+    let max = bounds[split + 1..].parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(format!("lower bound above upper bound in {}", bounds));
+    }
+    Ok((min, max))
+}
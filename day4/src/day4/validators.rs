@@ -0,0 +1,243 @@
+use super::report::FieldFailure;
+use super::schema::{FieldType, Schema};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A field type with its pattern compiled, matching whole values only.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    field_type: FieldType,
+    pattern: Option<Regex>,
+}
+
+impl Validator {
+    /// Compiles the type's pattern; fails if it is too large to compile,
+    /// e.g. for an absurd digit count.
+    pub fn new(field_type: &FieldType) -> Result<Validator, String> {
+        let pattern = match field_type {
+            FieldType::Year { .. } => Some(String::from(r"^[0-9]{4}$")),
+            FieldType::Measure(units) => {
+                let names: Vec<String> = units.iter().map(|u| regex::escape(&u.name)).collect();
+                Some(format!(r"^(-?[0-9]+)({})$", names.join("|")))
+            }
+            FieldType::HexColour => Some(String::from(r"^#[0-9a-f]{6}$")),
+            FieldType::Digits(count) => Some(format!(r"^[0-9]{{{}}}$", count)),
+            FieldType::Enum(_) | FieldType::Any => None,
+        };
+
+        let pattern = match pattern {
+            Some(p) => Some(Regex::new(&p).map_err(|e| match e {
+                regex::Error::CompiledTooBig(_) => String::from("pattern too large to compile"),
+                e => e.to_string(),
+            })?),
+            None => None,
+        };
+        Ok(Validator {
+            field_type: field_type.clone(),
+            pattern,
+        })
+    }
+
+    pub fn is_valid(&self, value: &str) -> bool {
+        self.check(value).is_ok()
+    }
+
+    /// Checks a value, explaining what is wrong with it, e.g. `above max 193`.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        match (&self.field_type, &self.pattern) {
+            (FieldType::Year { min, max }, Some(re)) => {
+                if !re.is_match(value) {
+                    return Err(String::from("not a four digit year"));
+                }
+                check_bounds(value, *min, *max)
+            }
+            (FieldType::Measure(units), Some(re)) => {
+                if let Some(caps) = re.captures(value) {
+                    if let Some(unit) = units.iter().find(|u| u.name == caps[2]) {
+                        return check_bounds(&caps[1], unit.min, unit.max);
+                    }
+                }
+                let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+                Err(format!("not a number in {}", names.join(" or ")))
+            }
+            (FieldType::HexColour, Some(re)) if !re.is_match(value) => {
+                Err(String::from("not a colour like #a1b2c3"))
+            }
+            (FieldType::Digits(count), Some(re)) if !re.is_match(value) => {
+                Err(format!("not a {} digit number", count))
+            }
+            (FieldType::Enum(values), _) if !values.iter().any(|v| v == value) => {
+                Err(format!("not one of {}", values.join(" ")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_bounds(value: &str, min: i32, max: i32) -> Result<(), String> {
+    match value.parse::<i32>() {
+        Ok(v) if v < min => Err(format!("below min {}", min)),
+        Ok(v) if v > max => Err(format!("above max {}", max)),
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("not a number")),
+    }
+}
+
+/// A check on a single field value, run after the field's built-in rule.
+pub trait FieldValidator: Send + Sync {
+    /// Explains what is wrong with the value, e.g. `checksum mismatch`. The
+    /// value is reported next to the reason and masked by its field when
+    /// redacted, so the reason must not quote it or any other value.
+    fn check(&self, value: &str) -> Result<(), String>;
+}
+
+/// A check across the fields of a whole passport.
+pub trait RecordValidator: Send + Sync {
+    /// Every field breaking the rule, each with the reason why. Reasons may
+    /// name other fields but must not quote their values, which only the
+    /// failing field's `value` carries.
+    fn check(&self, passport: &[(String, String)]) -> Vec<FieldFailure>;
+}
+
+impl FieldValidator for Validator {
+    fn check(&self, value: &str) -> Result<(), String> {
+        Validator::check(self, value)
+    }
+}
+
+impl<F> FieldValidator for F
+where
+    F: Fn(&str) -> Result<(), String> + Send + Sync,
+{
+    fn check(&self, value: &str) -> Result<(), String> {
+        self(value)
+    }
+}
+
+impl<F> RecordValidator for F
+where
+    F: Fn(&[(String, String)]) -> Vec<FieldFailure> + Send + Sync,
+{
+    fn check(&self, passport: &[(String, String)]) -> Vec<FieldFailure> {
+        self(passport)
+    }
+}
+
+/// Requires the year in `later` to come after the year in `earlier`, e.g. a
+/// passport expiring after it was issued. Passports missing either field, or
+/// holding something other than a number, are left to the field rules. The
+/// reason names `earlier` but not its value, which may be redacted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldOrder {
+    pub earlier: String,
+    pub later: String,
+}
+
+impl RecordValidator for FieldOrder {
+    fn check(&self, passport: &[(String, String)]) -> Vec<FieldFailure> {
+        let year = |field: &str| {
+            let value = &passport.iter().find(|f| f.0 == field)?.1;
+            Some((value, value.parse::<i64>().ok()?))
+        };
+        match (year(&self.earlier), year(&self.later)) {
+            (Some((_, earlier)), Some((value, later))) if later <= earlier => vec![FieldFailure {
+                field: self.later.clone(),
+                value: value.clone(),
+                reason: format!("not after {}", self.earlier),
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Validators for every field of a schema, compiled once and looked up by
+/// field name, along with any custom validators registered on top.
+#[derive(Clone, Default)]
+pub struct ValidatorSet {
+    validators: HashMap<String, Validator>,
+    custom: HashMap<String, Vec<Arc<dyn FieldValidator>>>,
+    records: Vec<Arc<dyn RecordValidator>>,
+}
+
+impl fmt::Debug for ValidatorSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ValidatorSet")
+            .field("validators", &self.validators)
+            .field("custom", &self.custom.keys().collect::<Vec<&String>>())
+            .field("records", &self.records.len())
+            .finish()
+    }
+}
+
+impl ValidatorSet {
+    /// Compiles a validator for every field of a schema, failing on the
+    /// first field whose pattern cannot be compiled.
+    pub fn new(schema: &Schema) -> Result<ValidatorSet, String> {
+        let validators = schema
+            .fields()
+            .iter()
+            .map(|rule| {
+                let validator = Validator::new(&rule.field_type)
+                    .map_err(|reason| format!("field {}: {}", rule.name, reason))?;
+                Ok((rule.name.clone(), validator))
+            })
+            .collect::<Result<Vec<(String, Validator)>, String>>()?;
+        Ok(ValidatorSet::from_validators(validators))
+    }
+
+    /// A set of already compiled validators, without custom ones.
+    pub fn from_validators<I>(validators: I) -> ValidatorSet
+    where
+        I: IntoIterator<Item = (String, Validator)>,
+    {
+        ValidatorSet {
+            validators: validators.into_iter().collect(),
+            custom: HashMap::new(),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Validator> {
+        self.validators.get(field)
+    }
+
+    pub fn add_field_validator<V>(&mut self, field: &str, validator: V)
+    where
+        V: FieldValidator + 'static,
+    {
+        self.custom
+            .entry(String::from(field))
+            .or_default()
+            .push(Arc::new(validator));
+    }
+
+    pub fn add_record_validator<V>(&mut self, validator: V)
+    where
+        V: RecordValidator + 'static,
+    {
+        self.records.push(Arc::new(validator));
+    }
+
+    /// Checks a field value against its built-in rule, then against custom
+    /// validators in the order they were added. Fields without any validator
+    /// are always valid.
+    pub fn check(&self, field: (&str, &str)) -> Result<(), String> {
+        if let Some(validator) = self.get(field.0) {
+            validator.check(field.1)?;
+        }
+        for validator in self.custom.get(field.0).into_iter().flatten() {
+            validator.check(field.1)?;
+        }
+        Ok(())
+    }
+
+    /// Runs every record validator over a passport.
+    pub fn check_record(&self, passport: &[(String, String)]) -> Vec<FieldFailure> {
+        self.records
+            .iter()
+            .flat_map(|validator| validator.check(passport))
+            .collect()
+    }
+}
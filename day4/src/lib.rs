@@ -1,158 +1,123 @@
 pub mod day4 {
-    use regex::Regex;
-    use std::io::BufRead;
+    pub mod formats;
+    pub mod generator;
+    pub mod passport;
+    pub mod policy;
+    pub mod reader;
+    pub mod redact;
+    pub mod repair;
+    pub mod report;
+    pub mod schema;
+    pub mod validators;
 
-    const REQUIRED_FIELDS: [&str; 7] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
+    use policy::FieldPolicy;
+    use reader::PassportReader;
+    use report::ValidationReport;
+    use schema::Schema;
+    use std::io::{self, BufRead};
+    use std::ops::RangeInclusive;
+    use std::sync::OnceLock;
 
-    pub fn is_passport_valid(passport: &Vec<(String, String)>) -> bool {
-        return REQUIRED_FIELDS
-            .iter()
-            .map(|f| (f, is_field_present(&passport, f)))
-            .filter(|f| f.1 == true)
-            .count()
-            == 7;
+    /// The rules of `default.schema`, parsed once.
+    pub fn default_schema() -> &'static Schema {
+        static SCHEMA: OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(Schema::default)
     }
 
-    pub fn is_passport_strictly_valid(passport: &Vec<(String, String)>) -> bool {
-        return REQUIRED_FIELDS
-            .iter()
-            .map(|f| get_field(&passport, f))
-            .map(|f| f.and_then(|p| Some(is_field_valid(p))).unwrap_or(false))
-            .filter(|&f| f == true)
-            .count()
-            == 7;
-    }
-
-    fn get_field<'a>(
-        passport: &'a Vec<(String, String)>,
-        field: &str,
-    ) -> Option<(&'a str, &'a str)> {
-        return passport
-            .iter()
-            .find(|f| f.0 == field)
-            .and_then(|f| Some((&f.0[..], &f.1[..])));
+    pub fn is_passport_valid(passport: &[(String, String)]) -> bool {
+        default_schema().is_passport_valid(passport)
     }
 
-    fn is_field_present(passport: &Vec<(String, String)>, field: &str) -> bool {
-        return passport.iter().find(|f| f.0 == field) != None;
+    /// Checks only the rules of [`default_schema`], which has no custom
+    /// validators; use [`Schema::is_passport_strictly_valid`] to include
+    /// those registered on a schema.
+    pub fn is_passport_strictly_valid(passport: &[(String, String)]) -> bool {
+        default_schema().is_passport_strictly_valid(passport)
     }
 
     pub fn is_field_valid(field: (&str, &str)) -> bool {
-        if field.0 == "byr" {
-            return is_byr_valid(&field.1);
-        } else if field.0 == "iyr" {
-            return is_iyr_valid(&field.1);
-        } else if field.0 == "eyr" {
-            return is_eyr_valid(&field.1);
-        } else if field.0 == "hgt" {
-            return is_hgt_valid(&field.1);
-        } else if field.0 == "hcl" {
-            return is_hcl_valid(&field.1);
-        } else if field.0 == "ecl" {
-            return is_ecl_valid(&field.1);
-        } else if field.0 == "pid" {
-            return is_pid_valid(&field.1);
-        }
-        true
-    }
-
-    fn is_byr_valid(value: &str) -> bool {
-        if let Ok(v) = value.parse::<i32>() {
-            return v >= 1920 && v <= 2002;
-        }
-        false
-    }
-
-    fn is_iyr_valid(value: &str) -> bool {
-        if let Ok(v) = value.parse::<i32>() {
-            return v >= 2010 && v <= 2020;
-        }
-        false
+        default_schema().is_field_valid(field)
     }
 
-    fn is_eyr_valid(value: &str) -> bool {
-        if let Ok(v) = value.parse::<i32>() {
-            return v >= 2020 && v <= 2030;
-        }
-        false
+    /// Panics if the input cannot be read; [`count_valid_passports_with`]
+    /// returns the error instead.
+    pub fn count_valid_passports(input: &mut dyn BufRead) -> usize {
+        count_valid_passports_with(input, default_schema()).unwrap()
     }
 
-    fn is_hgt_valid(value: &str) -> bool {
-        let re = Regex::new(r"(\d+)(in|cm)").unwrap();
-        let optional_caps = re.captures(value);
-        if let Some(caps) = optional_caps {
-            if &caps[2] == "cm" {
-                if let Ok(v) = &caps[1].parse::<i32>() {
-                    return *v >= 150 && *v <= 193;
-                }
-            } else if &caps[2] == "in" {
-                if let Ok(v) = &caps[1].parse::<i32>() {
-                    return *v >= 59 && *v <= 76;
-                }
-            }
-        }
-        false
+    /// Panics if the input cannot be read; [`count_strictly_valid_passports_with`]
+    /// returns the error instead.
+    pub fn count_strictly_valid_passports(input: &mut dyn BufRead) -> usize {
+        count_strictly_valid_passports_with(input, default_schema()).unwrap()
     }
 
-    fn is_hcl_valid(value: &str) -> bool {
-        let re = Regex::new(r"#[a-f0-9]{6}").unwrap();
-        return re.is_match(value);
+    pub fn count_valid_passports_with(
+        input: &mut dyn BufRead,
+        schema: &Schema,
+    ) -> io::Result<usize> {
+        count_passports(input, |r| schema.is_passport_valid(&r.fields))
     }
 
-    fn is_ecl_valid(value: &str) -> bool {
-        let colours = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-        return colours.iter().find(|&&c| c == value) != None;
+    pub fn count_strictly_valid_passports_with(
+        input: &mut dyn BufRead,
+        schema: &Schema,
+    ) -> io::Result<usize> {
+        count_passports(input, |r| {
+            schema.is_record_strictly_valid(r, &FieldPolicy::default())
+        })
     }
 
-    fn is_pid_valid(value: &str) -> bool {
-        let re = Regex::new(r"\b[0-9]{9}\b").unwrap();
-        return re.is_match(value);
+    fn count_passports<F>(input: &mut dyn BufRead, valid: F) -> io::Result<usize>
+    where
+        F: Fn(&Record) -> bool,
+    {
+        PassportReader::new(input)
+            .try_fold(0, |count, record| Ok(count + usize::from(valid(&record?))))
     }
 
-    pub fn count_valid_passports(input: &mut dyn BufRead) -> usize {
-        return parse_passports(input)
-            .iter()
-            .map(|p| is_passport_valid(p))
-            .filter(|&valid| valid == true)
-            .count();
+    /// Strictly validates every passport of a batch, explaining each rejection.
+    pub fn validate_passports(input: &mut dyn BufRead) -> io::Result<ValidationReport> {
+        validate_passports_with(input, default_schema())
     }
 
-    pub fn count_strictly_valid_passports(input: &mut dyn BufRead) -> usize {
-        return parse_passports(input)
-            .iter()
-            .map(|p| is_passport_strictly_valid(p))
-            .filter(|&valid| valid == true)
-            .count();
+    pub fn validate_passports_with(
+        input: &mut dyn BufRead,
+        schema: &Schema,
+    ) -> io::Result<ValidationReport> {
+        validate_passports_with_policy(input, schema, &FieldPolicy::default())
     }
 
-    fn parse_passports(input: &mut dyn BufRead) -> Vec<Vec<(String, String)>> {
-        let lines: Vec<String> = input.lines().map(|line| line.unwrap()).collect();
-        let passports = lines
-            .split(|line| line == "")
-            .map(|ll| parse_passport(ll))
-            .collect();
-        return passports;
+    pub fn validate_passports_with_policy(
+        input: &mut dyn BufRead,
+        schema: &Schema,
+        policy: &FieldPolicy,
+    ) -> io::Result<ValidationReport> {
+        ValidationReport::read(PassportReader::new(input), schema, policy)
     }
 
-    fn parse_passport(lines: &[String]) -> Vec<(String, String)> {
-        return lines
-            .iter()
-            .flat_map(|line| parse_passport_line(line))
-            .collect();
+    /// A passport's `key:value` fields and the lines of the batch file
+    /// they came from (1-based).
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Record {
+        pub fields: Vec<(String, String)>,
+        /// Tokens which are not `key:value` pairs.
+        pub malformed: Vec<String>,
+        pub lines: RangeInclusive<usize>,
     }
 
-    fn parse_passport_line(line: &String) -> Vec<(String, String)> {
-        let pairs: Vec<&str> = line.split(' ').collect();
-        return pairs.iter().map(|p| parse_pair(p)).collect();
+    /// Splits a batch file into `key:value` records, one per passport.
+    pub fn parse_passports(input: &mut dyn BufRead) -> io::Result<Vec<Vec<(String, String)>>> {
+        PassportReader::new(input).map(|r| Ok(r?.fields)).collect()
     }
 
-    fn parse_pair(pair: &str) -> (String, String) {
-        let parts: Vec<&str> = pair.splitn(2, ":").collect();
-        return (String::from(parts[0]), String::from(parts[1]));
+    /// Like [`parse_passports`], but keeps track of where each passport starts and ends.
+    pub fn parse_records(input: &mut dyn BufRead) -> io::Result<Vec<Record>> {
+        PassportReader::new(input).collect()
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_vec)]
 mod validator_tests {
     use super::day4;
 
@@ -225,7 +190,7 @@ mod validator_tests {
 mod input_tests {
     use super::day4;
     use std::fs::File;
-    use std::io::{BufReader};
+    use std::io::BufReader;
 
     #[test]
     fn fields_separated_by_space() {
@@ -289,7 +254,7 @@ mod input_tests {
     fn day4_input() {
         let mut f = BufReader::new(File::open("./day4.input").unwrap());
 
-        assert_eq!(day4::count_valid_passports(&mut f), 2);
+        assert_eq!(day4::count_valid_passports(&mut f), 256);
     }
 
     fn read_from_string(s: &str) -> &[u8] {
@@ -298,10 +263,11 @@ mod input_tests {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod strict_validator_tests {
     use super::day4;
     use std::fs::File;
-    use std::io::{BufReader};
+    use std::io::BufReader;
 
     #[test]
     fn birth_year_at_least_1920_and_at_most_2002() {
@@ -389,3 +355,1610 @@ mod strict_validator_tests {
         assert_eq!(day4::count_strictly_valid_passports(&mut f), 198);
     }
 }
+
+#[cfg(test)]
+mod schema_tests {
+    use super::day4;
+    use super::day4::schema::{FieldType, Schema, SchemaError, Unit};
+
+    #[test]
+    fn default_schema_matches_schema_file() {
+        assert_eq!(Schema::load("./default.schema").unwrap(), Schema::default());
+        assert_eq!(day4::default_schema().fields().len(), 8);
+        assert_eq!(day4::default_schema().required_fields().count(), 7);
+    }
+
+    #[test]
+    fn parses_every_field_type() {
+        let schema = Schema::parse(
+            "# comment\n\
+            \n\
+            byr required year 1920-2002\n\
+            hgt optional measure cm 150-193 in 59-76\n\
+            hcl required hex-colour\n\
+            ecl required enum amb blu\n\
+            pid required digits 9\n\
+            cid optional any",
+        )
+        .unwrap();
+
+        assert_eq!(
+            schema.field("byr").unwrap().field_type,
+            FieldType::Year {
+                min: 1920,
+                max: 2002
+            }
+        );
+        assert_eq!(
+            schema.field("hgt").unwrap().field_type,
+            FieldType::Measure(vec![
+                Unit {
+                    name: String::from("cm"),
+                    min: 150,
+                    max: 193
+                },
+                Unit {
+                    name: String::from("in"),
+                    min: 59,
+                    max: 76
+                }
+            ])
+        );
+        assert!(!schema.field("hgt").unwrap().required);
+        assert_eq!(
+            schema.field("hcl").unwrap().field_type,
+            FieldType::HexColour
+        );
+        assert_eq!(
+            schema.field("ecl").unwrap().field_type,
+            FieldType::Enum(vec![String::from("amb"), String::from("blu")])
+        );
+        assert_eq!(
+            schema.field("pid").unwrap().field_type,
+            FieldType::Digits(9)
+        );
+        assert_eq!(schema.field("cid").unwrap().field_type, FieldType::Any);
+    }
+
+    #[test]
+    fn measure_bounds_may_be_negative() {
+        let schema = Schema::parse("tmp required measure c -40--10 f -40-14").unwrap();
+
+        assert_eq!(
+            schema.field("tmp").unwrap().field_type,
+            FieldType::Measure(vec![
+                Unit {
+                    name: String::from("c"),
+                    min: -40,
+                    max: -10
+                },
+                Unit {
+                    name: String::from("f"),
+                    min: -40,
+                    max: 14
+                }
+            ])
+        );
+        let fields = vec![(String::from("tmp"), String::from("-12c"))];
+        assert!(schema.is_passport_strictly_valid(&fields));
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let errors = [
+            (
+                "byr required",
+                "line 1: expected a name, required|optional and a type",
+            ),
+            (
+                "byr mandatory year 1-2",
+                "line 1: expected required or optional, found mandatory",
+            ),
+            ("byr required date", "line 1: unknown field type date"),
+            (
+                "\nbyr required year 2002-1920",
+                "line 2: lower bound above upper bound in 2002-1920",
+            ),
+            (
+                "byr required year 900-2002",
+                "line 1: year bounds must lie within 1000-9999, found 900-2002",
+            ),
+            (
+                "byr required year 1920-20020",
+                "line 1: year bounds must lie within 1000-9999, found 1920-20020",
+            ),
+            (
+                "hgt required measure cm",
+                "line 1: measure expects units with bounds like cm 150-193",
+            ),
+            (
+                "pid required digits nine",
+                "line 1: expected a digit count, found nine",
+            ),
+            ("cid optional any 1-2", "line 1: any takes no bounds"),
+            (
+                "cid optional any\ncid required any",
+                "line 2: field cid listed twice",
+            ),
+            (
+                "pid required digits 99999999",
+                "line 1: pattern too large to compile",
+            ),
+        ];
+        for (schema, message) in errors.iter() {
+            let error = Schema::parse(schema).unwrap_err();
+            assert!(matches!(error, SchemaError::Invalid { .. }));
+            assert_eq!(error.to_string(), *message);
+        }
+    }
+
+    #[test]
+    fn other_authority_rules() {
+        let schema = Schema::parse(
+            "byr required year 1900-2020\n\
+            hgt required measure m 1-3\n\
+            eye optional enum blue green",
+        )
+        .unwrap();
+        let passport = [
+            (String::from("byr"), String::from("1910")),
+            (String::from("hgt"), String::from("2m")),
+            (String::from("pid"), String::from("not checked")),
+        ];
+
+        assert!(schema.is_passport_strictly_valid(&passport));
+        assert!(!day4::is_passport_valid(&passport));
+        assert!(!schema.is_field_valid(("eye", "brown")));
+        assert!(!schema.is_field_valid(("hgt", "180cm")));
+    }
+
+    #[test]
+    fn count_passports_with_custom_schema() {
+        let schema = Schema::parse("pid required digits 9").unwrap();
+        let mut input = "pid:123456789\n\
+            \n\
+            pid:12345\n\
+            \n\
+            byr:1920"
+            .as_bytes();
+
+        assert_eq!(
+            day4::count_strictly_valid_passports_with(&mut input, &schema).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn only_first_occurrence_of_a_field_is_validated() {
+        let schema = Schema::parse("pid required digits 9").unwrap();
+        let first_valid = [
+            (String::from("pid"), String::from("123456789")),
+            (String::from("pid"), String::from("12345")),
+        ];
+        let first_invalid = [
+            (String::from("pid"), String::from("12345")),
+            (String::from("pid"), String::from("123456789")),
+        ];
+
+        assert!(schema.is_passport_strictly_valid(&first_valid));
+        assert!(!schema.is_passport_strictly_valid(&first_invalid));
+    }
+}
+
+#[cfg(test)]
+mod passport_tests {
+    use super::day4;
+    use super::day4::passport::{EyeColor, HairColor, Height, Passport, PassportError};
+    use std::convert::TryFrom;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn converts_every_field() {
+        let record =
+            parse("ecl:gry pid:060033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm");
+        let passport = Passport::try_from(&record[..]).unwrap();
+
+        assert_eq!(passport.birth_year, 1937);
+        assert_eq!(passport.issue_year, 2017);
+        assert_eq!(passport.expiration_year, 2020);
+        assert_eq!(passport.height, Height::Cm(183));
+        assert_eq!(
+            passport.hair_color,
+            HairColor {
+                r: 0xff,
+                g: 0xff,
+                b: 0xfd
+            }
+        );
+        assert_eq!(passport.eye_color, EyeColor::Gry);
+        assert_eq!(passport.passport_id.to_string(), "060033327");
+        assert_eq!(passport.country_id, Some(String::from("147")));
+    }
+
+    #[test]
+    fn collects_every_conversion_error() {
+        let record = parse("hgt:59 ecl:zzz eyr:1972 hcl:#18171d pid:186cm iyr:2018 byr:1926");
+        let errors = Passport::try_from(&record[..]).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                PassportError::Invalid {
+                    field: String::from("hgt"),
+                    value: String::from("59"),
+                    expected: "a height in cm or in"
+                },
+                PassportError::Invalid {
+                    field: String::from("ecl"),
+                    value: String::from("zzz"),
+                    expected: "one of amb blu brn gry grn hzl oth"
+                },
+                PassportError::Invalid {
+                    field: String::from("pid"),
+                    value: String::from("186cm"),
+                    expected: "a nine digit number"
+                },
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "hgt 59 is not a height in cm or in");
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let record = parse("hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn hgt:59in");
+        let errors = Passport::try_from(&record[..]).unwrap_err();
+
+        assert_eq!(errors, vec![PassportError::Missing(String::from("byr"))]);
+        assert_eq!(errors[0].to_string(), "byr is missing");
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert!("#123abz".parse::<HairColor>().is_err());
+        assert!("#123abcd".parse::<HairColor>().is_err());
+        assert!("123abc".parse::<HairColor>().is_err());
+        assert!("150".parse::<Height>().is_err());
+        assert!("cm".parse::<Height>().is_err());
+        assert!("x150cm".parse::<Height>().is_err());
+        assert!("150mm".parse::<Height>().is_err());
+        assert_eq!("76in".parse::<Height>(), Ok(Height::In(76)));
+    }
+
+    #[test]
+    fn displays_in_input_format() {
+        let record = parse("hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f pid:087499704");
+        let passport = Passport::try_from(&record[..]).unwrap();
+
+        assert_eq!(
+            passport.to_string(),
+            "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704"
+        );
+    }
+
+    #[test]
+    fn round_trips_every_convertible_passport() {
+        let mut f = BufReader::new(File::open("./day4.input").unwrap());
+        let passports: Vec<Passport> = day4::parse_passports(&mut f)
+            .unwrap()
+            .iter()
+            .filter_map(|record| Passport::try_from(&record[..]).ok())
+            .collect();
+
+        assert!(!passports.is_empty());
+        for passport in passports {
+            let record = parse(&passport.to_string());
+            assert_eq!(Passport::try_from(&record[..]), Ok(passport));
+        }
+    }
+
+    fn parse(line: &str) -> Vec<(String, String)> {
+        day4::parse_passports(&mut line.as_bytes())
+            .unwrap()
+            .remove(0)
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::day4;
+    use super::day4::report::FieldFailure;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn records_keep_their_line_span() {
+        let records = day4::parse_records(
+            &mut "ecl:gry pid:860033327\n\
+            eyr:2020\n\
+            \n\
+            \n\
+            byr:1937"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].lines, 1..=2);
+        assert_eq!(records[0].fields.len(), 3);
+        assert_eq!(records[1].lines, 5..=5);
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_fields() {
+        let report = day4::validate_passports(
+            &mut "byr:1937 iyr:2017 eyr:2020 hgt:194cm hcl:#fffffd ecl:gry pid:860033327\n\
+            \n\
+            iyr:2017 eyr:2020 hgt:60 hcl:#fffffd\n\
+            ecl:zzz pid:860033327"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(report.passports.len(), 2);
+        assert_eq!(report.valid_count(), 0);
+
+        let first = &report.passports[0];
+        assert_eq!(first.ordinal, 1);
+        assert!(first.missing.is_empty());
+        assert_eq!(
+            first.invalid,
+            vec![FieldFailure {
+                field: String::from("hgt"),
+                value: String::from("194cm"),
+                reason: String::from("above max 193")
+            }]
+        );
+        assert_eq!(
+            first.to_string(),
+            "passport 1 (lines 1-1): hgt 194cm above max 193"
+        );
+
+        let second = &report.passports[1];
+        assert_eq!(second.lines, 3..=4);
+        assert_eq!(second.missing, vec![String::from("byr")]);
+        assert_eq!(
+            second.to_string(),
+            "passport 2 (lines 3-4): missing byr; hgt 60 not a number in cm or in; \
+            ecl zzz not one of amb blu brn gry grn hzl oth"
+        );
+    }
+
+    #[test]
+    fn aggregates_failures_per_field() {
+        let mut f = BufReader::new(File::open("./sample2.input").unwrap());
+        let report = day4::validate_passports(&mut f).unwrap();
+        let stats = report.field_stats(day4::default_schema());
+
+        assert_eq!(report.valid_count(), 3);
+        assert_eq!(report.rejected().count(), 4);
+        let hgt = stats.iter().find(|s| s.field == "hgt").unwrap();
+        assert_eq!((hgt.missing, hgt.invalid), (0, 2));
+        assert!((hgt.failure_rate - 2.0 / 7.0).abs() < 1e-9);
+        let cid = stats.iter().find(|s| s.field == "cid").unwrap();
+        assert_eq!(cid.failure_rate, 0.0);
+    }
+
+    #[test]
+    fn report_agrees_with_strict_count() {
+        let mut f = BufReader::new(File::open("./day4.input").unwrap());
+        assert_eq!(day4::validate_passports(&mut f).unwrap().valid_count(), 198);
+    }
+
+    #[test]
+    fn summary_lists_rejections_and_rates() {
+        let report = day4::validate_passports(
+            &mut "byr:1919 iyr:2017 eyr:2020 hgt:150cm hcl:#fffffd ecl:gry pid:860033327"
+                .as_bytes(),
+        )
+        .unwrap();
+        let summary = report.summary(day4::default_schema());
+        let lines: Vec<&str> = summary.lines().collect();
+
+        assert_eq!(lines[0], "passport 1 (lines 1-1): byr 1919 below min 1920");
+        assert_eq!(lines[1], "0 of 1 passports valid");
+        assert_eq!(lines[2], "byr missing 0 invalid 1 failure rate 100.0%");
+        assert_eq!(lines[3], "iyr missing 0 invalid 0 failure rate 0.0%");
+    }
+}
+
+#[cfg(test)]
+mod compiled_validator_tests {
+    use super::day4;
+    use super::day4::schema::FieldType;
+    use super::day4::validators::{Validator, ValidatorSet};
+
+    #[test]
+    fn whole_values_must_match() {
+        assert!(!day4::is_field_valid(("hcl", "#123abcde")));
+        assert!(!day4::is_field_valid(("hcl", "x#123abc")));
+        assert!(!day4::is_field_valid(("hgt", "x150cmy")));
+        assert!(!day4::is_field_valid(("hgt", "150cmin")));
+        assert!(!day4::is_field_valid(("pid", "012345678 ")));
+        assert!(!day4::is_field_valid(("pid", "a:012345678")));
+        assert!(!day4::is_field_valid(("byr", "+1950")));
+        assert!(day4::is_field_valid(("hcl", "#123abc")));
+        assert!(day4::is_field_valid(("hgt", "150cm")));
+    }
+
+    #[test]
+    fn explains_pattern_mismatches() {
+        let validators = ValidatorSet::new(day4::default_schema()).unwrap();
+
+        assert_eq!(
+            validators.check(("hcl", "#123abcde")),
+            Err(String::from("not a colour like #a1b2c3"))
+        );
+        assert_eq!(
+            validators.check(("hgt", "x150cmy")),
+            Err(String::from("not a number in cm or in"))
+        );
+        assert_eq!(
+            validators.check(("byr", "19500")),
+            Err(String::from("not a four digit year"))
+        );
+        assert_eq!(validators.check(("cid", "anything")), Ok(()));
+        assert_eq!(validators.check(("xyz", "anything")), Ok(()));
+    }
+
+    #[test]
+    fn single_validator_matches_field_type() {
+        let field_type = FieldType::Digits(4);
+        let validator = Validator::new(&field_type).unwrap();
+
+        for value in ["1234", "123", "12345", "12a4"].iter() {
+            assert_eq!(validator.check(value), field_type.check(value));
+        }
+        assert!(validator.is_valid("0000"));
+    }
+}
+
+#[cfg(test)]
+mod policy_tests {
+    use super::day4;
+    use super::day4::policy::{Action, DuplicatePolicy, FieldPolicy, Violation, ViolationKind};
+
+    const VALID: &str = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327";
+
+    #[test]
+    fn malformed_tokens_do_not_panic() {
+        let records = day4::parse_records(&mut "byr:1937  iyr2017 :x hgt:".as_bytes()).unwrap();
+
+        assert_eq!(
+            records[0].fields,
+            vec![
+                (String::from("byr"), String::from("1937")),
+                (String::from("hgt"), String::from(""))
+            ]
+        );
+        assert_eq!(
+            records[0].malformed,
+            vec![String::from("iyr2017"), String::from(":x")]
+        );
+    }
+
+    #[test]
+    fn malformed_tokens_reject_by_default() {
+        let input = format!("{} oops", VALID);
+        let report = day4::validate_passports(&mut input.as_bytes()).unwrap();
+
+        assert_eq!(report.valid_count(), 0);
+        assert_eq!(
+            report.passports[0].to_string(),
+            "passport 1 (lines 1-1): malformed token \"oops\""
+        );
+    }
+
+    #[test]
+    fn malformed_tokens_can_be_ignored_or_warned_about() {
+        let input = format!("{} oops", VALID);
+        let ignore = policy(DuplicatePolicy::FirstWins, Action::Ignore, Action::Ignore);
+        let warn = policy(DuplicatePolicy::FirstWins, Action::Ignore, Action::Warn);
+
+        let ignored = validate(&input, &ignore);
+        assert!(ignored.passports[0].is_valid());
+        assert!(ignored.passports[0].violations.is_empty());
+
+        let warned = validate(&input, &warn);
+        assert!(warned.passports[0].is_valid());
+        assert_eq!(
+            warned.passports[0].to_string(),
+            "passport 1 (lines 1-1): valid; warning: malformed token \"oops\""
+        );
+    }
+
+    #[test]
+    fn counts_agree_with_the_report() {
+        for input in [format!("{} hgt:194cm", VALID), format!("{} oops", VALID)].iter() {
+            let count = day4::count_strictly_valid_passports(&mut input.as_bytes());
+            let report = day4::validate_passports(&mut input.as_bytes()).unwrap();
+
+            assert_eq!(count, report.valid_count(), "{}", input);
+        }
+        assert_eq!(
+            day4::count_strictly_valid_passports(&mut format!("{} oops", VALID).as_bytes()),
+            0
+        );
+    }
+
+    #[test]
+    fn first_or_last_duplicate_wins() {
+        let input = format!("{} hgt:194cm", VALID);
+        let first = policy(DuplicatePolicy::FirstWins, Action::Ignore, Action::Reject);
+        let last = policy(DuplicatePolicy::LastWins, Action::Ignore, Action::Reject);
+
+        assert!(validate(&input, &first).passports[0].is_valid());
+        let report = validate(&input, &last);
+        assert_eq!(
+            report.passports[0].to_string(),
+            "passport 1 (lines 1-1): hgt 194cm above max 193"
+        );
+    }
+
+    #[test]
+    fn duplicates_can_be_rejected() {
+        let input = format!("{} hgt:183cm", VALID);
+        let reject = policy(DuplicatePolicy::Reject, Action::Ignore, Action::Reject);
+        let report = validate(&input, &reject);
+
+        assert!(!report.passports[0].is_valid());
+        assert_eq!(
+            report.passports[0].violations,
+            vec![Violation {
+                kind: ViolationKind::Duplicate {
+                    field: String::from("hgt"),
+                    values: vec![String::from("183cm"), String::from("183cm")]
+                },
+                rejects: true
+            }]
+        );
+        assert_eq!(
+            report.passports[0].to_string(),
+            "passport 1 (lines 1-1): hgt repeated (183cm, 183cm)"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_can_be_ignored_warned_about_or_rejected() {
+        let input = format!("{} nat:elf", VALID);
+
+        let ignored = validate(
+            &input,
+            &policy(DuplicatePolicy::FirstWins, Action::Ignore, Action::Reject),
+        );
+        assert!(ignored.passports[0].violations.is_empty());
+
+        let warned = validate(
+            &input,
+            &policy(DuplicatePolicy::FirstWins, Action::Warn, Action::Reject),
+        );
+        assert!(warned.passports[0].is_valid());
+        assert_eq!(
+            warned.passports[0].violations[0].to_string(),
+            "warning: unknown field nat"
+        );
+
+        let rejected = validate(
+            &input,
+            &policy(DuplicatePolicy::FirstWins, Action::Reject, Action::Reject),
+        );
+        assert!(!rejected.passports[0].is_valid());
+        assert_eq!(
+            rejected.passports[0].violations[0].to_string(),
+            "unknown field nat"
+        );
+    }
+
+    fn policy(duplicates: DuplicatePolicy, unknown: Action, malformed: Action) -> FieldPolicy {
+        FieldPolicy {
+            duplicates,
+            unknown,
+            malformed,
+        }
+    }
+
+    fn validate(input: &str, policy: &FieldPolicy) -> day4::report::ValidationReport {
+        day4::validate_passports_with_policy(&mut input.as_bytes(), day4::default_schema(), policy)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod reader_tests {
+    use super::day4;
+    use super::day4::reader::PassportReader;
+    use std::fs::File;
+    use std::io::{self, BufReader, Read};
+
+    #[test]
+    fn whitespace_only_lines_separate_passports() {
+        let records: Vec<day4::Record> = PassportReader::new(
+            "ecl:gry pid:860033327\n  \t\nbyr:1937\n\n\n\t\niyr:2017\n \n".as_bytes(),
+        )
+        .map(|r| r.unwrap())
+        .collect();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].lines, 1..=1);
+        assert_eq!(records[1].lines, 3..=3);
+        assert_eq!(records[2].lines, 7..=7);
+    }
+
+    #[test]
+    fn fields_separated_by_any_whitespace() {
+        let record = PassportReader::new(
+            "ecl:gry\tpid:860033327  eyr:2020\r\n\thcl:#fffffd \r\n".as_bytes(),
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            record.fields,
+            vec![
+                (String::from("ecl"), String::from("gry")),
+                (String::from("pid"), String::from("860033327")),
+                (String::from("eyr"), String::from("2020")),
+                (String::from("hcl"), String::from("#fffffd"))
+            ]
+        );
+        assert!(record.malformed.is_empty());
+        assert_eq!(record.lines, 1..=2);
+    }
+
+    #[test]
+    fn yields_passports_before_reading_the_rest() {
+        let input = "byr:1937\n\nbyr:1938\n".as_bytes().chain(Failing);
+        let mut reader = PassportReader::new(BufReader::new(input));
+
+        assert_eq!(reader.next().unwrap().unwrap().lines, 1..=1);
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn read_errors_reach_the_caller() {
+        let mut input = BufReader::new("byr:1937\n\nbyr:1938\n".as_bytes().chain(Failing));
+        let error = day4::validate_passports(&mut input).unwrap_err();
+        assert_eq!(error.to_string(), "disk on fire");
+
+        let mut input = BufReader::new("byr:1937\n".as_bytes().chain(Failing));
+        let schema = day4::default_schema();
+        assert!(day4::count_strictly_valid_passports_with(&mut input, schema).is_err());
+    }
+
+    #[test]
+    fn reader_agrees_with_counts() {
+        let f = BufReader::new(File::open("./day4.input").unwrap());
+        let strict = PassportReader::new(f)
+            .map(|r| r.unwrap())
+            .filter(|r| day4::is_passport_strictly_valid(&r.fields))
+            .count();
+
+        assert_eq!(strict, 198);
+    }
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod formats_tests {
+    use super::day4;
+    use super::day4::formats::{self, CsvReader, FormatError, JsonLinesReader};
+    use super::day4::passport::Passport;
+    use std::convert::TryFrom;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn reads_json_lines() {
+        let records: Vec<day4::Record> = JsonLinesReader::new(
+            "{\"byr\": \"1937\", \"iyr\": 2017, \"cid\": null}\n\
+            \n\
+            {\"hcl\":\"#fffffd\",\"nat\":\"caf\\u00e9 \\\"x\\\" \\ud83c\\udf84\"}\n\
+            {}"
+            .as_bytes(),
+        )
+        .map(|r| r.unwrap())
+        .collect();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].fields,
+            vec![
+                (String::from("byr"), String::from("1937")),
+                (String::from("iyr"), String::from("2017"))
+            ]
+        );
+        assert_eq!(records[1].lines, 3..=3);
+        assert_eq!(records[1].fields[1].1, "caf\u{e9} \"x\" \u{1f384}");
+        assert!(records[2].fields.is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_json_with_its_line() {
+        let errors = [
+            (
+                "{\"byr\": [1937]}",
+                "line 1: nested value at column 9, only flat objects are supported",
+            ),
+            (
+                "{\"byr\" \"1937\"}",
+                "line 1: expected ':' at column 8, found '\"'",
+            ),
+            (
+                "{\"byr\": \"1937\"",
+                "line 1: expected ',', found end of line",
+            ),
+            ("{\"byr\": nope}", "line 1: invalid value at column 9"),
+            ("{} []", "line 1: unexpected text after the object"),
+        ];
+        for (json, message) in errors.iter() {
+            let error = JsonLinesReader::new(json.as_bytes())
+                .next()
+                .unwrap()
+                .unwrap_err();
+            assert!(matches!(error, FormatError::Invalid { line: 1, .. }));
+            assert_eq!(error.to_string(), *message);
+        }
+    }
+
+    #[test]
+    fn json_numbers_follow_the_json_grammar() {
+        for number in ["0", "-0", "1937", "-1.5", "2e3", "6.02E+23", "1e-9"].iter() {
+            let json = format!("{{\"byr\": {}}}", number);
+            let record = JsonLinesReader::new(json.as_bytes()).next().unwrap();
+            assert_eq!(record.unwrap().fields[0].1, *number);
+        }
+        for literal in [
+            "nan", "inf", "infinity", "-inf", "+1", "01", "1.", ".5", "1e", "0x10",
+        ]
+        .iter()
+        {
+            let json = format!("{{\"byr\": {}}}", literal);
+            let error = JsonLinesReader::new(json.as_bytes())
+                .next()
+                .unwrap()
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "line 1: invalid value at column 9",
+                "{}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn batches_round_trip() {
+        let mut input = "byr:1937 nat:caf\u{e9} hgt: url:a:b\n\niyr:2017\n".as_bytes();
+        let records = day4::parse_records(&mut input).unwrap();
+
+        let mut batch = Vec::new();
+        formats::write_batch(&mut batch, &records).unwrap();
+
+        assert_eq!(day4::parse_records(&mut &batch[..]).unwrap(), records);
+    }
+
+    #[test]
+    fn batches_refuse_values_which_would_not_read_back() {
+        for (key, value) in [("nat", "North Pole"), ("n:t", "x"), ("n t", "x"), ("", "x")].iter() {
+            let record = day4::Record {
+                fields: vec![(String::from(*key), String::from(*value))],
+                malformed: Vec::new(),
+                lines: 1..=1,
+            };
+            let mut batch = Vec::new();
+            let error = formats::write_batch(&mut batch, &[record]).unwrap_err();
+
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn batches_refuse_empty_records() {
+        let records = [
+            day4::Record {
+                fields: vec![(String::from("byr"), String::from("1937"))],
+                malformed: Vec::new(),
+                lines: 1..=1,
+            },
+            day4::Record {
+                fields: Vec::new(),
+                malformed: Vec::new(),
+                lines: 3..=3,
+            },
+        ];
+        let mut batch = Vec::new();
+        let error = formats::write_batch(&mut batch, &records).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn reads_csv() {
+        let records: Vec<day4::Record> = CsvReader::new(
+            "byr,hgt,nat\n\
+            1937,183cm,\"North, Pole\"\n\
+            \n\
+            1938,,\"says \"\"hi\"\"\n\
+            twice\"\n\
+            1939,,"
+                .as_bytes(),
+        )
+        .map(|r| r.unwrap())
+        .collect();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].fields,
+            vec![
+                (String::from("byr"), String::from("1937")),
+                (String::from("hgt"), String::from("183cm")),
+                (String::from("nat"), String::from("North, Pole"))
+            ]
+        );
+        assert_eq!(records[0].lines, 2..=2);
+        assert_eq!(
+            records[1].fields,
+            vec![
+                (String::from("byr"), String::from("1938")),
+                (String::from("nat"), String::from("says \"hi\"\ntwice"))
+            ]
+        );
+        assert_eq!(records[1].lines, 4..=5);
+        assert_eq!(records[2].fields.len(), 1);
+    }
+
+    #[test]
+    fn reports_invalid_csv_rows() {
+        let mut reader = CsvReader::new("byr,hgt\n1937,183cm,extra\n1937\n1938,\"open".as_bytes());
+
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "line 2: 3 cells but 2 columns"
+        );
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "line 3: 1 cells but 2 columns"
+        );
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "line 4: unterminated quoted cell"
+        );
+
+        let mut reader = CsvReader::new("byr,,hgt\n1937,x,183cm".as_bytes());
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "line 1: column 2 has no name"
+        );
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let records =
+            day4::parse_records(&mut "byr:1937 nat:\"quoted\\path\"\n\nhgt:183cm".as_bytes())
+                .unwrap();
+        let mut out = Vec::new();
+        formats::write_json_lines(&mut out, &records).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "{\"byr\":\"1937\",\"nat\":\"\\\"quoted\\\\path\\\"\"}\n{\"hgt\":\"183cm\"}\n"
+        );
+        let read: Vec<Vec<(String, String)>> = JsonLinesReader::new(&out[..])
+            .map(|r| r.unwrap().fields)
+            .collect();
+        assert_eq!(
+            read,
+            day4::parse_passports(&mut "byr:1937 nat:\"quoted\\path\"\n\nhgt:183cm".as_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let records =
+            day4::parse_records(&mut "byr:1937 nat:a,\"b\"\n\nhgt:183cm".as_bytes()).unwrap();
+        let mut out = Vec::new();
+        formats::write_csv(&mut out, &["byr", "hgt", "nat"], &records).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "byr,hgt,nat\n1937,,\"a,\"\"b\"\"\"\n,183cm,\n"
+        );
+        let read: Vec<Vec<(String, String)>> = CsvReader::new(&out[..])
+            .map(|r| r.unwrap().fields)
+            .collect();
+        assert_eq!(
+            read,
+            vec![records[0].fields.clone(), records[1].fields.clone()]
+        );
+    }
+
+    #[test]
+    fn converted_batches_validate_the_same() {
+        let mut f = BufReader::new(File::open("./day4.input").unwrap());
+        let records = day4::parse_records(&mut f).unwrap();
+        let columns: Vec<&str> = day4::default_schema()
+            .fields()
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+
+        let mut json = Vec::new();
+        formats::write_json_lines(&mut json, &records).unwrap();
+        let mut csv = Vec::new();
+        formats::write_csv(&mut csv, &columns, &records).unwrap();
+        let mut batch = Vec::new();
+        formats::write_batch(&mut batch, &records).unwrap();
+
+        let strict = |records: Vec<day4::Record>| {
+            records
+                .iter()
+                .filter(|r| day4::is_passport_strictly_valid(&r.fields))
+                .count()
+        };
+        assert_eq!(
+            strict(
+                JsonLinesReader::new(&json[..])
+                    .map(|r| r.unwrap())
+                    .collect()
+            ),
+            198
+        );
+        assert_eq!(
+            strict(CsvReader::new(&csv[..]).map(|r| r.unwrap()).collect()),
+            198
+        );
+        assert_eq!(day4::count_strictly_valid_passports(&mut &batch[..]), 198);
+    }
+
+    #[test]
+    fn json_records_convert_to_typed_passports() {
+        let record = JsonLinesReader::new(
+            "{\"byr\":1937,\"iyr\":2017,\"eyr\":2020,\"hgt\":\"183cm\",\"hcl\":\"#fffffd\",\"ecl\":\"gry\",\"pid\":\"860033327\"}"
+                .as_bytes(),
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+
+        let passport = Passport::try_from(&record.fields[..]).unwrap();
+        assert_eq!(passport.birth_year, 1937);
+    }
+}
+
+#[cfg(test)]
+mod custom_validator_tests {
+    use super::day4;
+    use super::day4::report::{FieldFailure, ValidationReport};
+    use super::day4::schema::Schema;
+    use super::day4::validators::{FieldOrder, FieldValidator};
+
+    /// Passport IDs whose digits pass the Luhn check.
+    struct Luhn;
+
+    impl FieldValidator for Luhn {
+        fn check(&self, value: &str) -> Result<(), String> {
+            let sum: u32 = value
+                .chars()
+                .rev()
+                .filter_map(|c| c.to_digit(10))
+                .enumerate()
+                .map(|(i, d)| {
+                    if i % 2 == 1 {
+                        (d * 2) / 10 + (d * 2) % 10
+                    } else {
+                        d
+                    }
+                })
+                .sum();
+            if sum.is_multiple_of(10) {
+                Ok(())
+            } else {
+                Err(String::from("fails Luhn check"))
+            }
+        }
+    }
+
+    const PASSPORT: &str = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry";
+
+    #[test]
+    fn field_validator_runs_after_built_in_rule() {
+        let mut schema = Schema::default();
+        schema.register_field_validator("pid", Luhn);
+
+        assert!(schema.is_passport_strictly_valid(&passport("pid:000000018")));
+        assert!(!schema.is_passport_strictly_valid(&passport("pid:000000017")));
+        assert_eq!(
+            schema.check_field(("pid", "000000017")),
+            Err(String::from("fails Luhn check"))
+        );
+        assert_eq!(
+            schema.check_field(("pid", "18")),
+            Err(String::from("not a 9 digit number"))
+        );
+        assert!(day4::is_passport_strictly_valid(&passport("pid:000000017")));
+    }
+
+    #[test]
+    fn closures_are_field_validators() {
+        let mut schema = Schema::default();
+        schema.register_field_validator("hcl", |value: &str| {
+            if value == "#000000" {
+                Err(String::from("cannot be pitch black"))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(schema.is_field_valid(("hcl", "#000001")));
+        assert_eq!(
+            schema.check_field(("hcl", "#000000")),
+            Err(String::from("cannot be pitch black"))
+        );
+    }
+
+    #[test]
+    fn record_validator_compares_fields() {
+        let mut schema = Schema::default();
+        schema.register_record_validator(FieldOrder {
+            earlier: String::from("iyr"),
+            later: String::from("eyr"),
+        });
+        let expired = day4::parse_passports(
+            &mut "byr:1937 iyr:2020 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327"
+                .as_bytes(),
+        )
+        .unwrap()
+        .remove(0);
+
+        assert!(schema.is_passport_strictly_valid(&passport("pid:860033327")));
+        assert!(!schema.is_passport_strictly_valid(&expired));
+        assert_eq!(
+            schema.check_record(&expired),
+            vec![FieldFailure {
+                field: String::from("eyr"),
+                value: String::from("2020"),
+                reason: String::from("not after iyr")
+            }]
+        );
+    }
+
+    #[test]
+    fn custom_failures_appear_in_reports() {
+        let mut schema = Schema::default();
+        schema.register_field_validator("pid", Luhn);
+        schema.register_record_validator(|passport: &[(String, String)]| {
+            if passport.iter().any(|f| f.0 == "cid") {
+                Vec::new()
+            } else {
+                vec![FieldFailure {
+                    field: String::from("cid"),
+                    value: String::new(),
+                    reason: String::from("required by this authority"),
+                }]
+            }
+        });
+        let records =
+            day4::parse_records(&mut format!("{} pid:000000017", PASSPORT).as_bytes()).unwrap();
+        let report = ValidationReport::new(&records, &schema);
+
+        assert_eq!(
+            report.passports[0].to_string(),
+            "passport 1 (lines 1-1): pid 000000017 fails Luhn check; cid required by this authority"
+        );
+    }
+
+    fn passport(pid: &str) -> Vec<(String, String)> {
+        day4::parse_passports(&mut format!("{} {}", PASSPORT, pid).as_bytes())
+            .unwrap()
+            .remove(0)
+    }
+}
+
+#[cfg(test)]
+mod repair_tests {
+    use super::day4;
+    use super::day4::repair::{self, Confidence, Suggestion};
+    use super::day4::schema::Schema;
+
+    #[test]
+    fn hair_color_missing_hash() {
+        let suggestions = repair::suggest(&passport("hcl:123abc"), day4::default_schema());
+
+        assert_eq!(
+            suggestions,
+            vec![Suggestion {
+                field: String::from("hcl"),
+                original: String::from("123abc"),
+                proposed: String::from("#123abc"),
+                confidence: Confidence::High,
+                reason: String::from("added missing #")
+            }]
+        );
+        assert_eq!(
+            suggestions[0].to_string(),
+            "hcl 123abc -> #123abc (high confidence: added missing #)"
+        );
+    }
+
+    #[test]
+    fn height_without_unit() {
+        let cm = repair::suggest(&passport("hgt:183"), day4::default_schema());
+        let inches = repair::suggest(&passport("hgt:70"), day4::default_schema());
+        let neither = repair::suggest(&passport("hgt:100"), day4::default_schema());
+
+        assert_eq!(
+            (cm[0].proposed.as_str(), cm[0].confidence),
+            ("183cm", Confidence::Medium)
+        );
+        assert_eq!(inches[0].proposed, "70in");
+        assert!(neither.is_empty());
+    }
+
+    #[test]
+    fn passport_id_with_extra_leading_zero() {
+        let extra = repair::suggest(&passport("pid:0860033327"), day4::default_schema());
+        let short = repair::suggest(&passport("pid:60033327"), day4::default_schema());
+        let long = repair::suggest(&passport("pid:1860033327"), day4::default_schema());
+
+        assert_eq!(
+            (extra[0].proposed.as_str(), extra[0].confidence),
+            ("860033327", Confidence::Medium)
+        );
+        assert_eq!(
+            (short[0].proposed.as_str(), short[0].confidence),
+            ("060033327", Confidence::Low)
+        );
+        assert!(long.is_empty());
+    }
+
+    #[test]
+    fn case_and_whitespace() {
+        let schema = day4::default_schema();
+
+        assert_eq!(
+            repair::suggest(&passport("ecl:BRN"), schema)[0].proposed,
+            "brn"
+        );
+        assert_eq!(
+            repair::suggest(&passport("hcl:#ABCDEF"), schema)[0].proposed,
+            "#abcdef"
+        );
+        assert_eq!(
+            repair::suggest(&[(String::from("byr"), String::from(" 1937"))], schema)[0].proposed,
+            "1937"
+        );
+    }
+
+    #[test]
+    fn proposal_revalidates_without_touching_the_original() {
+        let original = passport("hcl:123abc hgt:183");
+        let proposal = repair::propose(&original, day4::default_schema());
+
+        assert_eq!(proposal.suggestions.len(), 2);
+        assert!(proposal.valid_after_repair);
+        assert!(day4::is_passport_strictly_valid(&proposal.repaired));
+        assert!(!day4::is_passport_strictly_valid(&original));
+        assert_eq!(original, passport("hcl:123abc hgt:183"));
+    }
+
+    #[test]
+    fn proposal_reports_passports_still_invalid() {
+        let proposal = repair::propose(&passport("hcl:123abc hgt:100"), day4::default_schema());
+
+        assert_eq!(proposal.suggestions.len(), 1);
+        assert!(!proposal.valid_after_repair);
+    }
+
+    #[test]
+    fn only_selected_suggestions_are_applied() {
+        let original = passport("hcl:123abc pid:0860033327");
+        let suggestions = repair::suggest(&original, day4::default_schema());
+        let high: Vec<Suggestion> = suggestions
+            .into_iter()
+            .filter(|s| s.confidence == Confidence::High)
+            .collect();
+        let repaired = repair::apply(&original, &high);
+
+        assert!(repaired.contains(&(String::from("hcl"), String::from("#123abc"))));
+        assert!(repaired.contains(&(String::from("pid"), String::from("0860033327"))));
+    }
+
+    #[test]
+    fn suggestions_respect_custom_validators() {
+        let mut schema = Schema::default();
+        schema.register_field_validator("hgt", |value: &str| {
+            if value.ends_with("in") {
+                Err(String::from("metric only"))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(repair::suggest(&passport("hgt:70"), &schema).is_empty());
+    }
+
+    /// A valid passport with some fields replaced.
+    fn passport(overrides: &str) -> Vec<(String, String)> {
+        let mut fields = day4::parse_passports(
+            &mut "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327"
+                .as_bytes(),
+        )
+        .unwrap()
+        .remove(0);
+        for (key, value) in day4::parse_passports(&mut overrides.as_bytes())
+            .unwrap()
+            .remove(0)
+        {
+            let field = fields.iter_mut().find(|f| f.0 == key).unwrap();
+            field.1 = value;
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod generator_tests {
+    use super::day4;
+    use super::day4::generator::{Generator, Kind, Mix};
+    use super::day4::policy::FieldPolicy;
+    use super::day4::schema::Schema;
+
+    #[test]
+    fn same_seed_same_batch() {
+        let schema = day4::default_schema();
+
+        assert_eq!(
+            Generator::new(schema, 7).batch(50),
+            Generator::new(schema, 7).batch(50)
+        );
+        assert_ne!(
+            Generator::new(schema, 7).batch(50),
+            Generator::new(schema, 8).batch(50)
+        );
+    }
+
+    #[test]
+    fn batch_follows_the_mix() {
+        let batch = Generator::new(day4::default_schema(), 1)
+            .with_mix(Mix {
+                valid: 5,
+                missing: 3,
+                boundary: 1,
+                malformed: 1,
+            })
+            .batch(100);
+        let count = |kind: Kind| batch.passports.iter().filter(|p| p.kind == kind).count();
+
+        assert_eq!(count(Kind::Valid), 50);
+        assert_eq!(count(Kind::MissingField), 30);
+        assert_eq!(count(Kind::Boundary), 10);
+        assert_eq!(count(Kind::Malformed), 10);
+    }
+
+    #[test]
+    fn leftovers_go_to_the_first_kinds() {
+        let batch = Generator::new(day4::default_schema(), 1).batch(3);
+        let kinds: Vec<Kind> = batch.passports.iter().map(|p| p.kind).collect();
+
+        assert_eq!(kinds.iter().filter(|k| **k == Kind::Valid).count(), 2);
+        assert!(kinds.contains(&Kind::MissingField));
+    }
+
+    #[test]
+    fn expected_outcomes_match_validation() {
+        let schema = day4::default_schema();
+        let batch = Generator::new(schema, 2020).batch(500);
+        let mut written: Vec<u8> = Vec::new();
+        batch.write(&mut written).unwrap();
+
+        let records = day4::parse_records(&mut written.as_slice()).unwrap();
+        assert_eq!(
+            records,
+            batch.records().cloned().collect::<Vec<day4::Record>>()
+        );
+        for passport in &batch.passports {
+            let fields = &passport.record.fields;
+            assert_eq!(schema.is_passport_valid(fields), passport.expected_valid);
+            assert_eq!(
+                schema.is_record_strictly_valid(&passport.record, &FieldPolicy::default()),
+                passport.expected_strictly_valid,
+                "{:?}",
+                passport
+            );
+        }
+        assert_eq!(
+            day4::count_valid_passports(&mut written.as_slice()),
+            batch.expected_valid_count()
+        );
+        assert_eq!(
+            day4::count_strictly_valid_passports(&mut written.as_slice()),
+            batch.expected_strictly_valid_count()
+        );
+    }
+
+    #[test]
+    fn extreme_bounds_are_expected_as_validation_finds_them() {
+        for rules in [
+            "byr required year 1000-9999\nhgt required measure cm -2147483648-2147483647",
+            "byr required year 1000-1000\nhgt required measure c -40--10",
+            "byr required year 9999-9999",
+        ]
+        .iter()
+        {
+            let schema = Schema::parse(rules).unwrap();
+            let batch = Generator::new(&schema, 11).batch(200);
+            for passport in &batch.passports {
+                assert_eq!(
+                    schema.is_record_strictly_valid(&passport.record, &FieldPolicy::default()),
+                    passport.expected_strictly_valid,
+                    "{}: {:?}",
+                    rules,
+                    passport
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_passports_include_malformed_tokens() {
+        let batch = Generator::new(day4::default_schema(), 4)
+            .with_mix(Mix {
+                valid: 0,
+                missing: 0,
+                boundary: 0,
+                malformed: 1,
+            })
+            .batch(50);
+        let mut written: Vec<u8> = Vec::new();
+        batch.write(&mut written).unwrap();
+
+        assert!(batch
+            .passports
+            .iter()
+            .any(|p| !p.record.malformed.is_empty()));
+        assert!(batch
+            .passports
+            .iter()
+            .any(|p| p.record.malformed.is_empty()));
+        assert_eq!(
+            day4::parse_records(&mut written.as_slice()).unwrap(),
+            batch.records().cloned().collect::<Vec<day4::Record>>()
+        );
+        assert_eq!(batch.expected_strictly_valid_count(), 0);
+    }
+
+    #[test]
+    fn boundary_passports_sit_on_or_just_past_a_bound() {
+        let batch = Generator::new(day4::default_schema(), 3)
+            .with_mix(Mix {
+                valid: 0,
+                missing: 0,
+                boundary: 1,
+                malformed: 0,
+            })
+            .batch(200);
+        let values: Vec<&str> = batch
+            .passports
+            .iter()
+            .map(|p| {
+                let field = p.field.as_deref().unwrap();
+                let value = p.record.fields.iter().find(|f| f.0 == field).unwrap();
+                value.1.as_str()
+            })
+            .collect();
+
+        for value in [
+            "1920", "2002", "1919", "2003", "150cm", "194cm", "59in", "77in",
+        ] {
+            assert!(values.contains(&value), "{} never generated", value);
+        }
+        assert!(batch.passports.iter().all(|p| p.expected_valid));
+    }
+
+    #[test]
+    fn writes_expected_outcomes() {
+        let schema = Schema::parse("byr required year 1920-2002").unwrap();
+        let batch = Generator::new(&schema, 5)
+            .with_mix(Mix {
+                valid: 1,
+                missing: 1,
+                boundary: 0,
+                malformed: 1,
+            })
+            .batch(3);
+        let mut expected: Vec<u8> = Vec::new();
+        batch.write_expected(&mut expected).unwrap();
+        let mut lines: Vec<String> = String::from_utf8(expected)
+            .unwrap()
+            .lines()
+            .map(|l| l.split_once(' ').map_or(l, |(_, rest)| rest).to_string())
+            .collect();
+        lines.sort();
+
+        assert_eq!(
+            lines,
+            vec![
+                "malformed byr true false",
+                "missing byr false false",
+                "passport kind field valid strictly-valid",
+                "valid - true true",
+            ]
+        );
+    }
+
+    #[test]
+    fn kinds_the_schema_cannot_produce_fall_back_to_valid() {
+        let schema = Schema::parse("cid optional any").unwrap();
+        let batch = Generator::new(&schema, 9).batch(20);
+
+        assert!(batch.passports.iter().all(|p| p.kind == Kind::Valid));
+        assert_eq!(batch.expected_strictly_valid_count(), 20);
+    }
+}
+
+#[cfg(test)]
+mod redact_tests {
+    use super::day4;
+    use super::day4::formats;
+    use super::day4::passport::{Passport, PassportError};
+    use super::day4::policy::{Action, DuplicatePolicy, FieldPolicy};
+    use super::day4::redact::{Mask, Redactor};
+    use super::day4::repair;
+    use super::day4::validators::FieldOrder;
+    use std::convert::TryFrom;
+
+    const PASSPORT: &str =
+        "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147";
+
+    #[test]
+    fn masks() {
+        assert_eq!(Mask::Keep.apply("1937"), Some(String::from("1937")));
+        assert_eq!(Mask::Hide.apply("1937"), Some(String::from("****")));
+        assert_eq!(Mask::KeepFirst(2).apply("1937"), Some(String::from("19**")));
+        assert_eq!(
+            Mask::KeepLast(3).apply("860033327"),
+            Some(String::from("******327"))
+        );
+        assert_eq!(Mask::KeepFirst(9).apply("1937"), Some(String::from("1937")));
+        assert_eq!(Mask::Drop.apply("147"), None);
+    }
+
+    #[test]
+    fn default_rules_mask_pid_and_byr_and_drop_cid() {
+        let record = &day4::parse_records(&mut PASSPORT.as_bytes()).unwrap()[0];
+        let mut out: Vec<u8> = Vec::new();
+        formats::write_batch(&mut out, &[Redactor::default().record(record)]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "byr:19** iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:*********\n"
+        );
+    }
+
+    #[test]
+    fn rules_are_configurable_per_field() {
+        let redactor = Redactor::default()
+            .with_mask("pid", Mask::KeepLast(2))
+            .with_mask("hcl", Mask::Drop)
+            .with_mask("cid", Mask::Keep);
+        let fields = &day4::parse_passports(&mut PASSPORT.as_bytes()).unwrap()[0];
+        let redacted = redactor.fields(fields);
+
+        assert_eq!(redactor.mask("pid"), Mask::KeepLast(2));
+        assert_eq!(redactor.mask("ecl"), Mask::Keep);
+        assert!(redacted.contains(&(String::from("pid"), String::from("*******27"))));
+        assert!(redacted.contains(&(String::from("cid"), String::from("147"))));
+        assert!(!redacted.iter().any(|f| f.0 == "hcl"));
+        assert!(Redactor::new().fields(fields) == *fields);
+    }
+
+    #[test]
+    fn redacts_typed_passports_and_conversion_errors() {
+        let fields = &day4::parse_passports(&mut PASSPORT.as_bytes()).unwrap()[0];
+        let passport = Passport::try_from(fields.as_slice()).unwrap();
+        let redacted = Redactor::default().passport(&passport);
+        let error = PassportError::Invalid {
+            field: String::from("pid"),
+            value: String::from("0123456789"),
+            expected: "a nine digit number",
+        };
+
+        assert_eq!(redacted[0], (String::from("byr"), String::from("19**")));
+        assert_eq!(redacted.len(), 7);
+        assert_eq!(
+            Redactor::default().error(&error).to_string(),
+            "pid ********** is not a nine digit number"
+        );
+    }
+
+    #[test]
+    fn redacts_reports() {
+        let input = "byr:2003 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:0123456789\n\
+                     cid:1 cid:2 secret860033327";
+        let policy = FieldPolicy {
+            duplicates: DuplicatePolicy::Reject,
+            unknown: Action::Ignore,
+            malformed: Action::Reject,
+        };
+        let report = day4::validate_passports_with_policy(
+            &mut input.as_bytes(),
+            day4::default_schema(),
+            &policy,
+        )
+        .unwrap();
+        let redacted = Redactor::default().report(&report);
+
+        assert_eq!(
+            redacted.passports[0].to_string(),
+            "passport 1 (lines 1-2): byr 20** above max 2002; \
+             pid ********** not a 9 digit number; \
+             malformed token \"***************\"; cid repeated"
+        );
+        assert_eq!(redacted.valid_count(), report.valid_count());
+    }
+
+    #[test]
+    fn record_validator_failures_are_masked_by_field() {
+        let mut schema = day4::schema::Schema::default();
+        schema.register_record_validator(FieldOrder {
+            earlier: String::from("iyr"),
+            later: String::from("byr"),
+        });
+        let input = "byr:2015 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327";
+        let report = day4::validate_passports_with(&mut input.as_bytes(), &schema).unwrap();
+
+        assert_eq!(
+            Redactor::default().report(&report).passports[0].to_string(),
+            "passport 1 (lines 1-1): byr 20** above max 2002; byr 20** not after iyr"
+        );
+    }
+
+    #[test]
+    fn redacts_suggestions() {
+        let passport = vec![
+            (String::from("pid"), String::from("0860033327")),
+            (String::from("cid"), String::from("147")),
+        ];
+        let suggestions = repair::suggest(&passport, day4::default_schema());
+        let redacted: Vec<String> = suggestions
+            .iter()
+            .filter_map(|s| Redactor::default().suggestion(s))
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(
+            redacted,
+            vec![format!(
+                "pid ********** -> ********* ({} confidence: {})",
+                suggestions[0].confidence, suggestions[0].reason
+            )]
+        );
+
+        let dropped = Redactor::new().with_mask("pid", Mask::Drop);
+        assert_eq!(dropped.suggestion(&suggestions[0]), None);
+    }
+
+    #[test]
+    fn dropped_fields_keep_their_failures_without_values() {
+        let mut schema = day4::schema::Schema::default();
+        schema.register_field_validator("cid", |_: &str| Err(String::from("not issued")));
+        let input =
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147";
+        let report = day4::validate_passports_with(&mut input.as_bytes(), &schema).unwrap();
+
+        assert_eq!(
+            Redactor::default().report(&report).passports[0].to_string(),
+            "passport 1 (lines 1-1): cid not issued"
+        );
+    }
+}
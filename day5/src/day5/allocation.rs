@@ -0,0 +1,268 @@
+use super::layout::PlaneLayout;
+use super::seatmap::{FreeSeat, Region, SeatMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// A seat given to a member of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupSeat {
+    pub row: i32,
+    pub column: i32,
+    pub seat_id: i32,
+    pub pass: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub seats: Vec<GroupSeat>,
+    pub score: i64,
+}
+
+impl Allocation {
+    pub fn seat_ids(&self) -> Vec<i32> {
+        self.seats.iter().map(|s| s.seat_id).collect()
+    }
+
+    pub fn passes(&self) -> Vec<&str> {
+        self.seats.iter().map(|s| s.pass.as_str()).collect()
+    }
+}
+
+/// Why a group could not be seated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllocationError {
+    EmptyGroup,
+    /// Fewer seats are free, outside any excluded regions, than there are
+    /// people in the group.
+    NotEnoughSeats {
+        requested: usize,
+        free: usize,
+    },
+    /// No row has enough free seats side by side; `largest_block` is the
+    /// most any row has.
+    NotTogether {
+        requested: usize,
+        largest_block: usize,
+    },
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AllocationError::EmptyGroup => write!(f, "the group is empty"),
+            AllocationError::NotEnoughSeats { requested, free } => write!(
+                f,
+                "{} seats requested but only {} are free",
+                requested, free
+            ),
+            AllocationError::NotTogether {
+                requested,
+                largest_block,
+            } => write!(
+                f,
+                "{} seats requested but at most {} free seats are side by side",
+                requested, largest_block
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocationError {}
+
+/// Rates a set of seats for a group, higher being better.
+pub type Scoring = Arc<dyn Fn(&[FreeSeat], &PlaneLayout) -> i64 + Send + Sync>;
+
+/// Prefers few rows, no free seats left between group members and not
+/// being split by the aisle, which runs down the middle of the plane.
+pub fn default_scoring(seats: &[FreeSeat], layout: &PlaneLayout) -> i64 {
+    let aisle = layout.columns() / 2;
+    let mut score = 0;
+    for pair in seats.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if a.row != b.row {
+            score -= 100;
+        } else {
+            score -= i64::from(b.column - a.column - 1);
+            if a.column < aisle && b.column >= aisle {
+                score -= 10;
+            }
+        }
+    }
+    score
+}
+
+/// Assigns free seats to travelling groups.
+#[derive(Clone)]
+pub struct Allocator<'a> {
+    map: &'a SeatMap,
+    scoring: Scoring,
+    allow_split: bool,
+    excluded: Vec<Region>,
+}
+
+impl fmt::Debug for Allocator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Allocator")
+            .field("map", &self.map)
+            .field("allow_split", &self.allow_split)
+            .field("excluded", &self.excluded)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Allocator<'a> {
+    pub fn new(map: &'a SeatMap) -> Allocator<'a> {
+        Allocator {
+            map,
+            scoring: Arc::new(default_scoring),
+            allow_split: false,
+            excluded: Vec::new(),
+        }
+    }
+
+    pub fn with_scoring<F>(mut self, scoring: F) -> Allocator<'a>
+    where
+        F: Fn(&[FreeSeat], &PlaneLayout) -> i64 + Send + Sync + 'static,
+    {
+        self.scoring = Arc::new(scoring);
+        self
+    }
+
+    /// Leaves the free seats of these regions out, e.g. the front and back
+    /// seats a plane keeps out of service. No region is excluded by default.
+    pub fn exclude_regions(mut self, regions: &[Region]) -> Allocator<'a> {
+        self.excluded = regions.to_vec();
+        self
+    }
+
+    /// Whether a group may be split over rows or gaps when no row has
+    /// enough free seats side by side.
+    pub fn allow_split(mut self, allow: bool) -> Allocator<'a> {
+        self.allow_split = allow;
+        self
+    }
+
+    /// The best scoring set of `group` free seats, front-most on ties.
+    /// Without splitting, the candidates are the runs of free seats side by
+    /// side in a row. With it, they are every choice of seats within a row
+    /// and across neighbouring rows, taking in only as many rows as the
+    /// group needs; the number of candidates grows quickly with the group.
+    pub fn allocate(&self, group: usize) -> Result<Allocation, AllocationError> {
+        if group == 0 {
+            return Err(AllocationError::EmptyGroup);
+        }
+        let free: Vec<FreeSeat> = self
+            .map
+            .free_seats()
+            .into_iter()
+            .filter(|s| !self.excluded.contains(&s.region))
+            .collect();
+        if free.len() < group {
+            return Err(AllocationError::NotEnoughSeats {
+                requested: group,
+                free: free.len(),
+            });
+        }
+
+        let layout = self.map.layout();
+        let rows: Vec<&[FreeSeat]> = free.chunk_by(|a, b| a.row == b.row).collect();
+        let mut best: Option<(Vec<FreeSeat>, i64)> = None;
+        let mut consider = |seats: &[FreeSeat]| {
+            let score = (self.scoring)(seats, layout);
+            let better = best.as_ref().is_none_or(|(best, best_score)| {
+                score > *best_score
+                    || (score == *best_score
+                        && seats
+                            .iter()
+                            .map(|s| s.seat_id)
+                            .lt(best.iter().map(|s| s.seat_id)))
+            });
+            if better {
+                best = Some((seats.to_vec(), score));
+            }
+        };
+
+        for (first, row) in rows.iter().enumerate() {
+            if !self.allow_split {
+                row.windows(group)
+                    .filter(|run| run.windows(2).all(|p| p[0].column + 1 == p[1].column))
+                    .for_each(&mut consider);
+                continue;
+            }
+            // Rows first..=last, each candidate taking at least one seat of
+            // both. A span always reaches the next row, and further rows only
+            // while the rows before its last cannot seat the whole group.
+            let mut before = 0;
+            for last in first..rows.len() {
+                if before >= group && last > first + 1 {
+                    break;
+                }
+                let span: Vec<FreeSeat> = rows[first..=last].concat();
+                let (top, bottom) = (rows[first][0].row, rows[last][0].row);
+                combinations(&span, group, &mut |seats| {
+                    if seats[0].row == top && seats[group - 1].row == bottom {
+                        consider(seats);
+                    }
+                });
+                before += rows[last].len();
+            }
+        }
+
+        match best {
+            Some((seats, score)) => Ok(Allocation {
+                seats: seats
+                    .iter()
+                    .map(|s| GroupSeat {
+                        row: s.row,
+                        column: s.column,
+                        seat_id: s.seat_id,
+                        pass: layout.encode(s.row, s.column).unwrap(),
+                    })
+                    .collect(),
+                score,
+            }),
+            None => Err(AllocationError::NotTogether {
+                requested: group,
+                largest_block: largest_block(&free),
+            }),
+        }
+    }
+}
+
+/// Calls `visit` with every choice of `count` seats, keeping seat order.
+fn combinations<F>(seats: &[FreeSeat], count: usize, visit: &mut F)
+where
+    F: FnMut(&[FreeSeat]),
+{
+    fn choose<F>(seats: &[FreeSeat], count: usize, chosen: &mut Vec<FreeSeat>, visit: &mut F)
+    where
+        F: FnMut(&[FreeSeat]),
+    {
+        if chosen.len() == count {
+            visit(chosen);
+            return;
+        }
+        let needed = count - chosen.len();
+        for i in 0..(seats.len() + 1).saturating_sub(needed) {
+            chosen.push(seats[i]);
+            choose(&seats[i + 1..], count, chosen, visit);
+            chosen.pop();
+        }
+    }
+    choose(seats, count, &mut Vec::with_capacity(count), visit);
+}
+
+/// The most free seats side by side in one row.
+fn largest_block(free: &[FreeSeat]) -> usize {
+    let mut largest = 0;
+    let mut block = 0;
+    for (i, seat) in free.iter().enumerate() {
+        let continues = i > 0 && {
+            let previous = &free[i - 1];
+            previous.row == seat.row && previous.column + 1 == seat.column
+        };
+        block = if continues { block + 1 } else { 1 };
+        largest = largest.max(block);
+    }
+    largest
+}
@@ -0,0 +1,74 @@
+use super::layout::{DecodeError, PlaneLayout};
+
+/// A boarding pass which decoded, with the line it was read from (1-based).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSeat {
+    pub line: usize,
+    pub pass: String,
+    pub row: i32,
+    pub column: i32,
+    pub seat_id: i32,
+}
+
+/// A line which is not a boarding pass for the plane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadLine {
+    pub line: usize,
+    pub text: String,
+    pub error: DecodeError,
+}
+
+/// Boarding passes decoded one per line, with the lines that failed to
+/// decode kept apart from the seats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    pub seats: Vec<DecodedSeat>,
+    pub bad_lines: Vec<BadLine>,
+}
+
+impl Batch {
+    /// Decodes every line, ignoring surrounding whitespace and blank lines.
+    pub fn decode<'a, I>(layout: &PlaneLayout, lines: I) -> Batch
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut batch = Batch {
+            seats: Vec::new(),
+            bad_lines: Vec::new(),
+        };
+        for (i, text) in lines.into_iter().enumerate() {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match layout.decode(text) {
+                Ok((row, column)) => batch.seats.push(DecodedSeat {
+                    line: i + 1,
+                    pass: String::from(text),
+                    row,
+                    column,
+                    seat_id: layout.seat_id(row, column),
+                }),
+                Err(error) => batch.bad_lines.push(BadLine {
+                    line: i + 1,
+                    text: String::from(text),
+                    error,
+                }),
+            }
+        }
+        batch
+    }
+
+    pub fn seat_ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.seats.iter().map(|s| s.seat_id)
+    }
+
+    /// One line per bad line, like `line 3: "FBFBBFFRXR" invalid letter 'X'
+    /// at position 9, expected L or R`.
+    pub fn error_report(&self) -> String {
+        self.bad_lines
+            .iter()
+            .map(|bad| format!("line {}: {:?} {}\n", bad.line, bad.text, bad.error))
+            .collect()
+    }
+}
@@ -0,0 +1,245 @@
+//! Passengers boarding through a single aisle, entering at the front row.
+//! Time advances in ticks; each passenger walks the aisle one row at a time,
+//! waits when the next row of the aisle is taken, and blocks the aisle at
+//! their own row while stowing luggage and getting past seated passengers.
+
+use super::layout::PlaneLayout;
+
+/// Durations in ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// Walking past one row; at least 1.
+    pub walk: u32,
+    /// Stowing luggage once at the right row.
+    pub stow: u32,
+    /// Per seated passenger between the aisle and the passenger's seat.
+    pub shuffle: u32,
+}
+
+impl Default for Timings {
+    fn default() -> Timings {
+        Timings {
+            walk: 1,
+            stow: 4,
+            shuffle: 2,
+        }
+    }
+}
+
+/// In which order passengers are called to board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardingOrder {
+    /// As they arrived.
+    Manifest,
+    /// Rows from the back, passengers of a row as they arrived.
+    BackToFront,
+    /// Window seats first, then the next seats in, the aisle seats last.
+    WindowMiddleAisle,
+    /// Shuffled from a seed.
+    Random(u64),
+}
+
+impl BoardingOrder {
+    /// The seats in this boarding order.
+    pub fn arrange(&self, layout: &PlaneLayout, seats: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        let mut ordered = seats.to_vec();
+        match self {
+            BoardingOrder::Manifest => {}
+            BoardingOrder::BackToFront => ordered.sort_by_key(|&(row, _)| -row),
+            BoardingOrder::WindowMiddleAisle => {
+                ordered.sort_by_key(|&(_, column)| -aisle_distance(layout, column))
+            }
+            BoardingOrder::Random(seed) => {
+                let mut rng = Rng::new(*seed);
+                for i in (1..ordered.len()).rev() {
+                    let j = rng.below(i as u64 + 1) as usize;
+                    ordered.swap(i, j);
+                }
+            }
+        }
+        ordered
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardingReport {
+    /// Ticks until the last passenger sat down.
+    pub total_time: u32,
+    /// Ticks passengers spent stuck in the aisle at each row, front first.
+    pub row_congestion: Vec<u32>,
+}
+
+impl BoardingReport {
+    pub fn total_congestion(&self) -> u32 {
+        self.row_congestion.iter().sum()
+    }
+
+    /// The row where passengers were stuck longest, the front-most on ties,
+    /// or `None` if nobody was ever stuck.
+    pub fn most_congested_row(&self) -> Option<i32> {
+        let max = *self.row_congestion.iter().max()?;
+        if max == 0 {
+            return None;
+        }
+        self.row_congestion
+            .iter()
+            .position(|c| *c == max)
+            .map(|row| row as i32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Simulation {
+    layout: PlaneLayout,
+    timings: Timings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Walking,
+    Settling,
+}
+
+#[derive(Debug)]
+struct Passenger {
+    row: i32,
+    column: i32,
+    position: i32,
+    busy: u32,
+    state: State,
+}
+
+impl Simulation {
+    pub fn new(layout: PlaneLayout) -> Simulation {
+        Simulation {
+            layout,
+            timings: Timings::default(),
+        }
+    }
+
+    pub fn with_timings(mut self, timings: Timings) -> Simulation {
+        self.timings = timings;
+        self
+    }
+
+    /// Boards passengers in the given order. Seats outside the plane are
+    /// skipped.
+    pub fn run(&self, seats: &[(i32, i32)]) -> BoardingReport {
+        let rows = self.layout.rows();
+        let walk = self.timings.walk.max(1);
+        let mut queue = seats
+            .iter()
+            .filter(|&&(row, column)| {
+                (0..rows).contains(&row) && (0..self.layout.columns()).contains(&column)
+            })
+            .map(|&(row, column)| Passenger {
+                row,
+                column,
+                position: 0,
+                busy: walk,
+                state: State::Walking,
+            });
+        let mut aisle: Vec<Option<Passenger>> = (0..rows).map(|_| None).collect();
+        let mut seated: Vec<(i32, i32)> = Vec::new();
+        let mut congestion = vec![0; rows as usize];
+        let mut waiting = queue.next();
+        let mut time = 0;
+
+        while waiting.is_some() || aisle.iter().any(|p| p.is_some()) {
+            time += 1;
+            if aisle[0].is_none() {
+                aisle[0] = waiting.take();
+                waiting = queue.next();
+            }
+
+            for position in (0..rows as usize).rev() {
+                let mut passenger = match aisle[position].take() {
+                    Some(p) => p,
+                    None => continue,
+                };
+                passenger.busy = passenger.busy.saturating_sub(1);
+                if passenger.busy > 0 {
+                    aisle[position] = Some(passenger);
+                    continue;
+                }
+
+                if passenger.state == State::Walking && passenger.row == passenger.position {
+                    passenger.state = State::Settling;
+                    passenger.busy = self.timings.stow
+                        + self.timings.shuffle * self.blocking(&seated, &passenger);
+                }
+                match passenger.state {
+                    State::Settling if passenger.busy == 0 => {
+                        seated.push((passenger.row, passenger.column));
+                    }
+                    State::Settling => aisle[position] = Some(passenger),
+                    State::Walking if aisle[position + 1].is_none() => {
+                        passenger.position += 1;
+                        passenger.busy = walk;
+                        aisle[position + 1] = Some(passenger);
+                    }
+                    State::Walking => {
+                        congestion[position] += 1;
+                        aisle[position] = Some(passenger);
+                    }
+                }
+            }
+        }
+
+        BoardingReport {
+            total_time: time,
+            row_congestion: congestion,
+        }
+    }
+
+    /// Seated passengers of the same row and side sitting closer to the
+    /// aisle than the passenger.
+    fn blocking(&self, seated: &[(i32, i32)], passenger: &Passenger) -> u32 {
+        let left = is_left(&self.layout, passenger.column);
+        let distance = aisle_distance(&self.layout, passenger.column);
+        seated
+            .iter()
+            .filter(|&&(row, column)| {
+                row == passenger.row
+                    && is_left(&self.layout, column) == left
+                    && aisle_distance(&self.layout, column) < distance
+            })
+            .count() as u32
+    }
+}
+
+/// Whether a column is left of the aisle, which runs down the middle.
+fn is_left(layout: &PlaneLayout, column: i32) -> bool {
+    column < layout.columns() / 2
+}
+
+/// Seats between a column and the aisle.
+fn aisle_distance(layout: &PlaneLayout, column: i32) -> i32 {
+    let half = layout.columns() / 2;
+    if column < half {
+        half - 1 - column
+    } else {
+        column - half
+    }
+}
+
+/// SplitMix64, seeded by the caller. Kept here rather than taken from a
+/// crate so a seed gives the same boarding order in every release.
+#[derive(Debug)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) % bound
+    }
+}
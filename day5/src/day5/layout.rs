@@ -0,0 +1,305 @@
+use std::fmt;
+
+/// How boarding passes of an aircraft type encode seats: a binary space
+/// partition over the rows, then over the columns, one letter per bit.
+///
+/// The default is the puzzle's plane: 7 row letters `F`/`B`, 3 column
+/// letters `L`/`R` and a seat ID of `row * 8 + column`.
+///
+/// Layouts are checked when built, so every seat has an ID which fits an
+/// `i32` and no two seats share one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneLayout {
+    row_bits: u32,
+    column_bits: u32,
+    row_letters: (char, char),
+    column_letters: (char, char),
+    id_multiplier: i32,
+}
+
+impl Default for PlaneLayout {
+    fn default() -> PlaneLayout {
+        PlaneLayout::new(7, 3).unwrap()
+    }
+}
+
+impl PlaneLayout {
+    /// A plane with `2^row_bits` rows and `2^column_bits` columns, using the
+    /// puzzle's letters, with seat IDs numbering the seats row by row.
+    pub fn new(row_bits: u32, column_bits: u32) -> Result<PlaneLayout, LayoutError> {
+        if row_bits + column_bits > MAX_BITS {
+            return Err(LayoutError::TooManySeats {
+                row_bits,
+                column_bits,
+            });
+        }
+        PlaneLayout {
+            row_bits,
+            column_bits,
+            row_letters: ('F', 'B'),
+            column_letters: ('L', 'R'),
+            id_multiplier: 1 << column_bits,
+        }
+        .check()
+    }
+
+    pub fn with_row_letters(
+        mut self,
+        lower: char,
+        upper: char,
+    ) -> Result<PlaneLayout, LayoutError> {
+        self.row_letters = (lower, upper);
+        self.check()
+    }
+
+    pub fn with_column_letters(
+        mut self,
+        lower: char,
+        upper: char,
+    ) -> Result<PlaneLayout, LayoutError> {
+        self.column_letters = (lower, upper);
+        self.check()
+    }
+
+    /// Seat IDs become `row * multiplier + column`; the multiplier must be
+    /// at least the number of columns.
+    pub fn with_id_multiplier(mut self, multiplier: i32) -> Result<PlaneLayout, LayoutError> {
+        self.id_multiplier = multiplier;
+        self.check()
+    }
+
+    fn check(self) -> Result<PlaneLayout, LayoutError> {
+        for letters in [self.row_letters, self.column_letters].iter() {
+            if letters.0 == letters.1 {
+                return Err(LayoutError::SameLetters(letters.0));
+            }
+        }
+        if self.id_multiplier < self.columns() {
+            return Err(LayoutError::MultiplierTooSmall {
+                multiplier: self.id_multiplier,
+                columns: self.columns(),
+            });
+        }
+        let highest_id = i64::from(self.rows() - 1) * i64::from(self.id_multiplier)
+            + i64::from(self.columns() - 1);
+        if highest_id > i64::from(i32::MAX) {
+            return Err(LayoutError::IdOverflow { highest_id });
+        }
+        Ok(self)
+    }
+
+    /// The letters picking the lower and the upper half of the rows.
+    pub fn row_letters(&self) -> (char, char) {
+        self.row_letters
+    }
+
+    /// The letters picking the lower and the upper half of the columns.
+    pub fn column_letters(&self) -> (char, char) {
+        self.column_letters
+    }
+
+    /// Seat IDs are `row * id_multiplier + column`.
+    pub fn id_multiplier(&self) -> i32 {
+        self.id_multiplier
+    }
+
+    pub fn rows(&self) -> i32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> i32 {
+        1 << self.column_bits
+    }
+
+    /// Length of a boarding pass for this plane.
+    pub fn pass_length(&self) -> usize {
+        (self.row_bits + self.column_bits) as usize
+    }
+
+    pub fn seat_id(&self, row: i32, column: i32) -> i32 {
+        row * self.id_multiplier + column
+    }
+
+    /// Decodes a boarding pass into its seat ID.
+    pub fn decode_seat_id(&self, boarding_pass: &str) -> Result<i32, DecodeError> {
+        let (row, column) = self.decode(boarding_pass)?;
+        Ok(self.seat_id(row, column))
+    }
+
+    /// The boarding pass for a seat, or `None` if the plane has no such seat.
+    pub fn encode(&self, row: i32, column: i32) -> Option<String> {
+        if !(0..self.rows()).contains(&row) || !(0..self.columns()).contains(&column) {
+            return None;
+        }
+        let mut pass = encode_position(row, self.row_bits, self.row_letters);
+        pass.push_str(&encode_position(
+            column,
+            self.column_bits,
+            self.column_letters,
+        ));
+        Some(pass)
+    }
+
+    /// The boarding pass for a seat ID, or `None` if no seat has that ID.
+    pub fn encode_seat_id(&self, seat_id: i32) -> Option<String> {
+        if seat_id < 0 || self.id_multiplier <= 0 {
+            return None;
+        }
+        self.encode(seat_id / self.id_multiplier, seat_id % self.id_multiplier)
+    }
+
+    /// Decodes a boarding pass into `(row, column)`.
+    pub fn decode(&self, boarding_pass: &str) -> Result<(i32, i32), DecodeError> {
+        let letters: Vec<char> = boarding_pass.chars().collect();
+        if letters.len() != self.pass_length() {
+            return Err(DecodeError::WrongLength {
+                expected: self.pass_length(),
+                found: letters.len(),
+            });
+        }
+        let (row, column) = letters.split_at(self.row_bits as usize);
+        let row = find_position(row, self.row_letters, 0)?;
+        let column = find_position(column, self.column_letters, self.row_bits as usize)?;
+        Ok((row, column))
+    }
+}
+
+/// A pass may hold at most this many letters, so that rows, columns and the
+/// number of seats all fit an `i32`.
+const MAX_BITS: u32 = 30;
+
+/// Why a plane layout cannot be built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// More than `2^30` seats.
+    TooManySeats { row_bits: u32, column_bits: u32 },
+    /// Both halves are picked by the same letter.
+    SameLetters(char),
+    /// Seats of neighbouring rows would share IDs.
+    MultiplierTooSmall { multiplier: i32, columns: i32 },
+    /// The last seat's ID does not fit an `i32`.
+    IdOverflow { highest_id: i64 },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::TooManySeats {
+                row_bits,
+                column_bits,
+            } => write!(
+                f,
+                "{} row and {} column letters give more than 2^{} seats",
+                row_bits, column_bits, MAX_BITS
+            ),
+            LayoutError::SameLetters(letter) => {
+                write!(
+                    f,
+                    "{:?} cannot pick both the lower and the upper half",
+                    letter
+                )
+            }
+            LayoutError::MultiplierTooSmall {
+                multiplier,
+                columns,
+            } => write!(
+                f,
+                "seat ID multiplier {} is below the {} columns",
+                multiplier, columns
+            ),
+            LayoutError::IdOverflow { highest_id } => {
+                write!(f, "highest seat ID {} does not fit an i32", highest_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Why a string is not a boarding pass for a plane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The pass has `found` letters instead of `expected`.
+    WrongLength { expected: usize, found: usize },
+    /// The letter at `position` (1-based) is neither of the letters
+    /// `expected` there.
+    InvalidLetter {
+        position: usize,
+        letter: char,
+        expected: (char, char),
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::WrongLength { expected, found } => {
+                write!(f, "expected {} letters, found {}", expected, found)
+            }
+            DecodeError::InvalidLetter {
+                position,
+                letter,
+                expected,
+            } => write!(
+                f,
+                "invalid letter {:?} at position {}, expected {} or {}",
+                letter, position, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Narrows the range of positions down letter by letter. `offset` is the
+/// number of letters of the pass before `directions`, for error positions.
+fn find_position(
+    directions: &[char],
+    (lower, upper): (char, char),
+    offset: usize,
+) -> Result<i32, DecodeError> {
+    let mut range = (0, (1 << directions.len()) - 1);
+    for (i, &d) in directions.iter().enumerate() {
+        if d != lower && d != upper {
+            return Err(DecodeError::InvalidLetter {
+                position: offset + i + 1,
+                letter: d,
+                expected: (lower, upper),
+            });
+        }
+        range = bsp(range, d == lower);
+    }
+
+    Ok(range.0)
+}
+
+/// The letters narrowing the range of positions down to `position`, most
+/// significant bit first.
+fn encode_position(position: i32, bits: u32, (lower, upper): (char, char)) -> String {
+    (0..bits)
+        .rev()
+        .map(|bit| {
+            if position >> bit & 1 == 1 {
+                upper
+            } else {
+                lower
+            }
+        })
+        .collect()
+}
+
+fn bsp(range: (i32, i32), lower: bool) -> (i32, i32) {
+    let a = if lower {
+        range.0
+    } else {
+        (range.0 + range.1 + 1) / 2
+    };
+
+    let b = if lower {
+        (range.0 + range.1 - 1) / 2
+    } else {
+        range.1
+    };
+
+    (a, b)
+}
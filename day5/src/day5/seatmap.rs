@@ -0,0 +1,190 @@
+use super::layout::PlaneLayout;
+
+/// Where a free seat is. Planes leave some seats at the very front and back
+/// out of service, so only free seats between the first and the last taken
+/// seat can be somebody's seat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// Before the first taken seat.
+    Front,
+    Interior,
+    /// After the last taken seat.
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeSeat {
+    pub row: i32,
+    pub column: i32,
+    pub seat_id: i32,
+    pub region: Region,
+}
+
+/// Outcome of looking for the one free seat whose neighbouring seat IDs are
+/// both taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MySeat {
+    Found(i32),
+    NoCandidate,
+    /// More than one seat fits; their IDs in increasing order.
+    Ambiguous(Vec<i32>),
+}
+
+/// Which seats of a plane are taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatMap {
+    layout: PlaneLayout,
+    occupied: Vec<bool>,
+}
+
+impl SeatMap {
+    /// An empty plane.
+    pub fn new(layout: PlaneLayout) -> SeatMap {
+        SeatMap {
+            layout,
+            occupied: vec![false; (layout.rows() * layout.columns()) as usize],
+        }
+    }
+
+    /// Marks the seat of every boarding pass as taken, skipping strings
+    /// which do not decode.
+    pub fn from_passes<'a, I>(layout: PlaneLayout, boarding_passes: I) -> SeatMap
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut map = SeatMap::new(layout);
+        for pass in boarding_passes {
+            if let Ok((row, column)) = layout.decode(pass) {
+                map.occupy(row, column);
+            }
+        }
+        map
+    }
+
+    pub fn layout(&self) -> &PlaneLayout {
+        &self.layout
+    }
+
+    /// Marks a seat as taken, returning `false` if it was already taken or
+    /// is not on the plane.
+    pub fn occupy(&mut self, row: i32, column: i32) -> bool {
+        match self.index(row, column) {
+            Some(i) if !self.occupied[i] => {
+                self.occupied[i] = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_occupied(&self, row: i32, column: i32) -> bool {
+        self.index(row, column).is_some_and(|i| self.occupied[i])
+    }
+
+    pub fn occupied_count(&self) -> usize {
+        self.occupied.iter().filter(|o| **o).count()
+    }
+
+    /// Every free seat, front to back.
+    pub fn free_seats(&self) -> Vec<FreeSeat> {
+        let taken = self.seat_ids();
+        let mut free = Vec::new();
+        for row in 0..self.layout.rows() {
+            for column in 0..self.layout.columns() {
+                if !self.is_occupied(row, column) {
+                    let seat_id = self.layout.seat_id(row, column);
+                    free.push(FreeSeat {
+                        row,
+                        column,
+                        seat_id,
+                        region: region(seat_id, &taken),
+                    });
+                }
+            }
+        }
+        free
+    }
+
+    /// Interior free seats whose seat IDs one below and one above are both
+    /// taken.
+    pub fn my_seat(&self) -> MySeat {
+        let taken: Vec<i32> = self.seat_ids();
+        let mut candidates: Vec<i32> = self
+            .free_seats()
+            .iter()
+            .filter(|seat| seat.region == Region::Interior)
+            .map(|seat| seat.seat_id)
+            .filter(|id| {
+                taken.binary_search(&(id - 1)).is_ok() && taken.binary_search(&(id + 1)).is_ok()
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        match candidates.len() {
+            0 => MySeat::NoCandidate,
+            1 => MySeat::Found(candidates[0]),
+            _ => MySeat::Ambiguous(candidates),
+        }
+    }
+
+    /// The cabin, one line per row from the front: `#` for a taken seat,
+    /// `.` for a free one and `~` for seats out of service at the front or
+    /// back.
+    ///
+    /// ```text
+    /// 0 ~~~~
+    /// 1 #.##
+    /// 2 ####
+    /// 3 ~~~~
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        let width = (self.layout.rows() - 1).to_string().len();
+        let taken = self.seat_ids();
+        let mut out = String::new();
+        for row in 0..self.layout.rows() {
+            out.push_str(&format!("{:>width$} ", row, width = width));
+            for column in 0..self.layout.columns() {
+                let seat_id = self.layout.seat_id(row, column);
+                out.push(if self.is_occupied(row, column) {
+                    '#'
+                } else if region(seat_id, &taken) == Region::Interior {
+                    '.'
+                } else {
+                    '~'
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// IDs of the taken seats in increasing order.
+    fn seat_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = (0..self.layout.rows())
+            .flat_map(|row| (0..self.layout.columns()).map(move |column| (row, column)))
+            .filter(|&(row, column)| self.is_occupied(row, column))
+            .map(|(row, column)| self.layout.seat_id(row, column))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn index(&self, row: i32, column: i32) -> Option<usize> {
+        if (0..self.layout.rows()).contains(&row) && (0..self.layout.columns()).contains(&column) {
+            Some((row * self.layout.columns() + column) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// The region of a free seat, given the IDs of the taken seats in
+/// increasing order. With no seat taken every seat counts as the front.
+fn region(seat_id: i32, taken: &[i32]) -> Region {
+    match (taken.first(), taken.last()) {
+        (Some(first), _) if seat_id < *first => Region::Front,
+        (_, Some(last)) if seat_id > *last => Region::Back,
+        (Some(_), _) => Region::Interior,
+        _ => Region::Front,
+    }
+}
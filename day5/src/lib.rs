@@ -1,5 +1,13 @@
 pub mod day5 {
-    use regex::Regex;
+    pub mod allocation;
+    pub mod batch;
+    pub mod boarding;
+    pub mod layout;
+    pub mod seatmap;
+
+    use batch::Batch;
+    use layout::PlaneLayout;
+    use seatmap::{MySeat, SeatMap};
     use std::io::{self, BufRead};
     use std::{fs::File, path::Path};
 
@@ -7,12 +15,14 @@ pub mod day5 {
     where
         P: AsRef<Path>,
     {
-        let boarding_passes: Vec<String> = read_lines(input_file);
-        return boarding_passes
-            .iter()
-            .map(|p| find_seat_id(p))
-            .max()
-            .unwrap_or(0);
+        find_highest_seat_id_with(input_file, &PlaneLayout::default())
+    }
+
+    pub fn find_highest_seat_id_with<P>(input_file: P, layout: &PlaneLayout) -> i32
+    where
+        P: AsRef<Path>,
+    {
+        read_batch(input_file, layout).seat_ids().max().unwrap_or(0)
     }
 
     fn read_lines<P>(filename: P) -> Vec<String>
@@ -25,74 +35,77 @@ pub mod day5 {
                 .map(|v| v.unwrap())
                 .collect();
         }
-        return Vec::new();
+        Vec::new()
     }
 
+    /// The first seat ID missing between two taken ones, or 0 if there is
+    /// none; [`find_my_seat`] tells those cases apart.
     pub fn find_my_seat_id<P>(input_file: P) -> i32
     where
         P: AsRef<Path>,
     {
-        let boarding_passes: Vec<String> = read_lines(input_file);
-        let mut seat_ids: Vec<i32> = boarding_passes.iter().map(|p| find_seat_id(p)).collect();
+        find_my_seat_id_with(input_file, &PlaneLayout::default())
+    }
+
+    pub fn find_my_seat_id_with<P>(input_file: P, layout: &PlaneLayout) -> i32
+    where
+        P: AsRef<Path>,
+    {
+        let mut seat_ids: Vec<i32> = read_batch(input_file, layout).seat_ids().collect();
 
         seat_ids.sort_unstable();
 
-        return seat_ids
+        seat_ids
             .windows(2)
             .find(|w| w[0] + 2 == w[1])
-            .and_then(|w| Some(w[0] + 1))
-            .unwrap_or(0);
+            .map(|w| w[0] + 1)
+            .unwrap_or(0)
     }
 
-    pub fn find_seat_id(boarding_pass: &str) -> i32 {
-        let location = find_seat(boarding_pass);
-        return location.0 * 8 + location.1;
+    pub fn find_my_seat<P>(input_file: P) -> MySeat
+    where
+        P: AsRef<Path>,
+    {
+        read_seat_map(input_file, PlaneLayout::default()).my_seat()
     }
 
-    pub fn find_seat(boarding_pass: &str) -> (i32, i32) {
-        let re = Regex::new(r"^([FB]{7})([LR]{3})$").unwrap();
-        if let Some(caps) = re.captures(boarding_pass) {
-            return (find_row(&caps[1]), find_column(&caps[2]));
-        }
-        return (0, 0);
+    /// Decodes the boarding passes of a file, one per line, keeping the lines
+    /// which are not passes apart.
+    pub fn read_batch<P>(input_file: P, layout: &PlaneLayout) -> Batch
+    where
+        P: AsRef<Path>,
+    {
+        let lines: Vec<String> = read_lines(input_file);
+        Batch::decode(layout, lines.iter().map(|l| l.as_str()))
     }
 
-    fn find_row(directions: &str) -> i32 {
-        let mut range = (0, 127);
-        for d in directions.chars() {
-            range = bsp(range, d == 'F');
-        }
-
-        range.0
+    /// Marks the seat of every boarding pass in a file as taken, skipping
+    /// lines which are not passes.
+    pub fn read_seat_map<P>(input_file: P, layout: PlaneLayout) -> SeatMap
+    where
+        P: AsRef<Path>,
+    {
+        let boarding_passes: Vec<String> = read_lines(input_file);
+        SeatMap::from_passes(layout, boarding_passes.iter().map(|p| p.as_str()))
     }
 
-    fn find_column(directions: &str) -> i32 {
-        let mut range = (0, 7);
-        for d in directions.chars() {
-            range = bsp(range, d == 'L');
-        }
-
-        range.0
+    #[deprecated(note = "bad passes give seat ID 0; use `PlaneLayout::decode_seat_id`")]
+    pub fn find_seat_id(boarding_pass: &str) -> i32 {
+        PlaneLayout::default()
+            .decode_seat_id(boarding_pass)
+            .unwrap_or(0)
     }
 
-    fn bsp(range: (i32, i32), lower: bool) -> (i32, i32) {
-        let a = if lower {
-            range.0
-        } else {
-            (range.0 + range.1 + 1) / 2
-        };
-
-        let b = if lower {
-            (range.0 + range.1 - 1) / 2
-        } else {
-            range.1
-        };
-
-        (a, b)
+    #[deprecated(note = "bad passes decode to (0, 0); use `PlaneLayout::decode`")]
+    pub fn find_seat(boarding_pass: &str) -> (i32, i32) {
+        PlaneLayout::default()
+            .decode(boarding_pass)
+            .unwrap_or((0, 0))
     }
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::day5;
 
@@ -123,3 +136,609 @@ mod tests {
         assert_eq!(day5::find_my_seat_id("./day5.input"), 565);
     }
 }
+
+#[cfg(test)]
+mod layout_tests {
+    use super::day5;
+    use super::day5::layout::{LayoutError, PlaneLayout};
+
+    #[test]
+    fn default_layout_is_the_puzzle_plane() {
+        let layout = PlaneLayout::default();
+
+        assert_eq!((layout.rows(), layout.columns()), (128, 8));
+        assert_eq!(layout.pass_length(), 10);
+        assert_eq!(layout.decode("FBFBBFFRLR"), Ok((44, 5)));
+        assert_eq!(layout.seat_id(44, 5), 357);
+    }
+
+    #[test]
+    fn smaller_plane_with_its_own_letters() {
+        let layout = PlaneLayout::new(5, 2)
+            .unwrap()
+            .with_row_letters('A', 'Z')
+            .unwrap()
+            .with_column_letters('W', 'E')
+            .unwrap();
+
+        assert_eq!((layout.rows(), layout.columns()), (32, 4));
+        assert_eq!(layout.decode("AZAZZEW"), Ok((11, 2)));
+        assert_eq!(layout.decode_seat_id("AZAZZEW"), Ok(46));
+        assert_eq!(layout.decode("ZZZZZEE"), Ok((31, 3)));
+    }
+
+    #[test]
+    fn seat_id_formula() {
+        let layout = PlaneLayout::default().with_id_multiplier(10).unwrap();
+
+        assert_eq!(layout.decode_seat_id("FBFBBFFRLR"), Ok(445));
+        assert_eq!(
+            day5::find_highest_seat_id_with("./sample.input", &layout),
+            1024
+        );
+    }
+
+    #[test]
+    fn passes_for_another_plane_are_rejected() {
+        let layout = PlaneLayout::new(5, 2).unwrap();
+
+        assert!(layout.decode("FBFBBFFRLR").is_err());
+        assert!(layout.decode("FBFBBLX").is_err());
+        assert!(layout.decode_seat_id("FBFBBLX").is_err());
+        assert!(PlaneLayout::default().decode("FBFBBRR").is_err());
+    }
+
+    #[test]
+    fn layouts_are_checked_when_built() {
+        assert_eq!(
+            PlaneLayout::new(40, 3),
+            Err(LayoutError::TooManySeats {
+                row_bits: 40,
+                column_bits: 3
+            })
+        );
+        assert!(PlaneLayout::new(20, 10).is_ok());
+        assert_eq!(
+            PlaneLayout::new(5, 2).unwrap().with_row_letters('A', 'A'),
+            Err(LayoutError::SameLetters('A'))
+        );
+        assert_eq!(
+            PlaneLayout::default().with_id_multiplier(0),
+            Err(LayoutError::MultiplierTooSmall {
+                multiplier: 0,
+                columns: 8
+            })
+        );
+        assert_eq!(
+            PlaneLayout::new(20, 2)
+                .unwrap()
+                .with_id_multiplier(1 << 12)
+                .unwrap_err()
+                .to_string(),
+            "highest seat ID 4294963203 does not fit an i32"
+        );
+    }
+
+    #[test]
+    fn my_seat_with_default_layout() {
+        assert_eq!(
+            day5::find_my_seat_id_with("./day5.input", &PlaneLayout::default()),
+            565
+        );
+    }
+}
+
+#[cfg(test)]
+mod encode_tests {
+    use super::day5::layout::PlaneLayout;
+
+    #[test]
+    fn sample_passes() {
+        let layout = PlaneLayout::default();
+
+        assert_eq!(layout.encode(44, 5).as_deref(), Some("FBFBBFFRLR"));
+        assert_eq!(layout.encode_seat_id(567).as_deref(), Some("BFFFBBFRRR"));
+        assert_eq!(layout.encode_seat_id(119).as_deref(), Some("FFFBBBFRRR"));
+        assert_eq!(layout.encode_seat_id(820).as_deref(), Some("BBFFBBFRLL"));
+    }
+
+    #[test]
+    fn seats_outside_the_plane() {
+        let layout = PlaneLayout::default();
+
+        assert_eq!(layout.encode(128, 0), None);
+        assert_eq!(layout.encode(0, 8), None);
+        assert_eq!(layout.encode(-1, 0), None);
+        assert_eq!(layout.encode_seat_id(1024), None);
+        assert_eq!(layout.encode_seat_id(-1), None);
+    }
+
+    #[test]
+    fn ids_between_rows_have_no_seat() {
+        let layout = PlaneLayout::new(4, 2)
+            .unwrap()
+            .with_id_multiplier(10)
+            .unwrap();
+
+        assert_eq!(layout.encode_seat_id(13).as_deref(), Some("FFFBRR"));
+        assert_eq!(layout.encode_seat_id(14), None);
+    }
+
+    #[test]
+    fn every_seat_round_trips() {
+        let layout = PlaneLayout::default();
+        for row in 0..layout.rows() {
+            for column in 0..layout.columns() {
+                let pass = layout.encode(row, column).unwrap();
+                assert_eq!(layout.decode(&pass), Ok((row, column)));
+
+                let id = layout.seat_id(row, column);
+                assert_eq!(layout.encode_seat_id(id), Some(pass.clone()));
+                assert_eq!(layout.decode_seat_id(&pass), Ok(id));
+            }
+        }
+    }
+
+    #[test]
+    fn every_seat_of_another_plane_round_trips() {
+        let layout = PlaneLayout::new(5, 2)
+            .unwrap()
+            .with_row_letters('A', 'Z')
+            .unwrap()
+            .with_column_letters('W', 'E')
+            .unwrap()
+            .with_id_multiplier(6)
+            .unwrap();
+        for row in 0..layout.rows() {
+            for column in 0..layout.columns() {
+                let pass = layout.encode(row, column).unwrap();
+                assert_eq!(pass.len(), layout.pass_length());
+                assert_eq!(layout.decode(&pass), Ok((row, column)));
+                assert_eq!(
+                    layout.encode_seat_id(layout.decode_seat_id(&pass).unwrap()),
+                    Some(pass)
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod seatmap_tests {
+    use super::day5;
+    use super::day5::layout::PlaneLayout;
+    use super::day5::seatmap::{FreeSeat, MySeat, Region, SeatMap};
+
+    /// A 4 by 4 plane with rows 1 and 2 taken except seat 1,1.
+    fn small_plane() -> SeatMap {
+        let layout = PlaneLayout::new(2, 2).unwrap();
+        let mut map = SeatMap::new(layout);
+        for id in (4..12).filter(|id| *id != 5) {
+            map.occupy(id / 4, id % 4);
+        }
+        map
+    }
+
+    #[test]
+    fn renders_the_cabin() {
+        assert_eq!(small_plane().to_ascii(), "0 ~~~~\n1 #.##\n2 ####\n3 ~~~~\n");
+    }
+
+    #[test]
+    fn free_seats_by_region() {
+        let free = small_plane().free_seats();
+
+        assert_eq!(free.len(), 9);
+        assert_eq!(
+            free[4],
+            FreeSeat {
+                row: 1,
+                column: 1,
+                seat_id: 5,
+                region: Region::Interior
+            }
+        );
+        assert!(free[..4].iter().all(|s| s.region == Region::Front));
+        assert!(free[5..].iter().all(|s| s.region == Region::Back));
+    }
+
+    #[test]
+    fn finds_my_seat() {
+        assert_eq!(small_plane().my_seat(), MySeat::Found(5));
+        assert_eq!(day5::find_my_seat("./day5.input"), MySeat::Found(565));
+    }
+
+    #[test]
+    fn no_candidate_is_explicit() {
+        let mut full = small_plane();
+        full.occupy(1, 1);
+
+        assert_eq!(full.my_seat(), MySeat::NoCandidate);
+        assert_eq!(
+            SeatMap::new(PlaneLayout::default()).my_seat(),
+            MySeat::NoCandidate
+        );
+        assert_eq!(day5::find_my_seat("./sample.input"), MySeat::NoCandidate);
+        assert_eq!(day5::find_my_seat_id("./sample.input"), 0);
+    }
+
+    #[test]
+    fn several_candidates_are_ambiguous() {
+        let mut map = SeatMap::new(PlaneLayout::new(2, 2).unwrap());
+        for id in [4, 6, 8, 10, 11] {
+            assert!(map.occupy(id / 4, id % 4));
+        }
+
+        assert!(!map.occupy(1, 0));
+        assert_eq!(map.my_seat(), MySeat::Ambiguous(vec![5, 7, 9]));
+    }
+
+    #[test]
+    fn seat_map_of_the_input() {
+        let map = day5::read_seat_map("./day5.input", PlaneLayout::default());
+        let free = map.free_seats();
+        let interior: Vec<&FreeSeat> = free
+            .iter()
+            .filter(|s| s.region == Region::Interior)
+            .collect();
+
+        assert_eq!(map.occupied_count(), 805);
+        assert_eq!(interior.len(), 1);
+        assert_eq!(interior[0].seat_id, 565);
+        assert_eq!(map.to_ascii().lines().count(), 128);
+    }
+}
+
+#[cfg(test)]
+mod decode_tests {
+    use super::day5;
+    use super::day5::batch::Batch;
+    use super::day5::layout::{DecodeError, PlaneLayout};
+    use super::day5::seatmap::SeatMap;
+
+    #[test]
+    fn decodes_passes() {
+        assert_eq!(PlaneLayout::default().decode("FBFBBFFRLR"), Ok((44, 5)));
+        assert_eq!(PlaneLayout::default().decode("FFFFFFFLLL"), Ok((0, 0)));
+    }
+
+    #[test]
+    fn wrong_length() {
+        let error = PlaneLayout::default().decode("FBFBBFFRL").unwrap_err();
+
+        assert_eq!(
+            error,
+            DecodeError::WrongLength {
+                expected: 10,
+                found: 9
+            }
+        );
+        assert_eq!(error.to_string(), "expected 10 letters, found 9");
+    }
+
+    #[test]
+    fn invalid_letter() {
+        let layout = PlaneLayout::default();
+
+        assert_eq!(
+            layout.decode("FBFBBFFRXR"),
+            Err(DecodeError::InvalidLetter {
+                position: 9,
+                letter: 'X',
+                expected: ('L', 'R')
+            })
+        );
+        assert_eq!(
+            layout.decode("FBLBBFFRLR").unwrap_err().to_string(),
+            "invalid letter 'L' at position 3, expected F or B"
+        );
+        assert!(layout.decode_seat_id("FBLBBFFRLR").is_err());
+    }
+
+    #[test]
+    fn batch_keeps_bad_lines_apart() {
+        let batch = Batch::decode(
+            &PlaneLayout::default(),
+            vec!["FBFBBFFRLR", "", "garbage", " BBFFBBFRLL\r", "FBFBBFFRXR"],
+        );
+
+        assert_eq!(batch.seat_ids().collect::<Vec<i32>>(), vec![357, 820]);
+        assert_eq!(batch.seats[1].line, 4);
+        assert_eq!(batch.seats[1].pass, "BBFFBBFRLL");
+        assert_eq!(
+            batch
+                .bad_lines
+                .iter()
+                .map(|b| b.line)
+                .collect::<Vec<usize>>(),
+            vec![3, 5]
+        );
+        assert_eq!(
+            batch.error_report(),
+            "line 3: \"garbage\" expected 10 letters, found 7\n\
+             line 5: \"FBFBBFFRXR\" invalid letter 'X' at position 9, expected L or R\n"
+        );
+    }
+
+    #[test]
+    fn bad_passes_do_not_take_seat_zero() {
+        let map = SeatMap::from_passes(PlaneLayout::default(), vec!["FBFBBFFRLR", "garbage"]);
+
+        assert_eq!(map.occupied_count(), 1);
+        assert!(!map.is_occupied(0, 0));
+    }
+
+    #[test]
+    fn input_has_no_bad_lines() {
+        let batch = day5::read_batch("./day5.input", &PlaneLayout::default());
+
+        assert!(batch.bad_lines.is_empty());
+        assert_eq!(batch.seats.len(), 805);
+    }
+}
+
+#[cfg(test)]
+mod boarding_tests {
+    use super::day5;
+    use super::day5::boarding::{BoardingOrder, BoardingReport, Simulation, Timings};
+    use super::day5::layout::PlaneLayout;
+
+    fn simulate(seats: &[(i32, i32)]) -> BoardingReport {
+        Simulation::new(PlaneLayout::default()).run(seats)
+    }
+
+    #[test]
+    fn single_passenger_walks_and_stows() {
+        let report = simulate(&[(10, 0)]);
+
+        assert_eq!(report.total_time, 15);
+        assert_eq!(report.total_congestion(), 0);
+        assert_eq!(report.most_congested_row(), None);
+    }
+
+    #[test]
+    fn passengers_wait_behind_someone_stowing() {
+        let report = simulate(&[(5, 0), (10, 0)]);
+
+        assert_eq!(report.total_time, 20);
+        assert_eq!(report.row_congestion[4], 4);
+        assert_eq!(report.total_congestion(), 4);
+        assert_eq!(report.most_congested_row(), Some(4));
+    }
+
+    #[test]
+    fn back_to_front_avoids_the_wait() {
+        let seats = BoardingOrder::BackToFront.arrange(&PlaneLayout::default(), &[(5, 0), (10, 0)]);
+        let report = simulate(&seats);
+
+        assert_eq!(seats, vec![(10, 0), (5, 0)]);
+        assert_eq!(report.total_time, 15);
+        assert_eq!(report.total_congestion(), 0);
+    }
+
+    #[test]
+    fn getting_past_seated_passengers_takes_time() {
+        assert_eq!(simulate(&[(0, 3), (0, 0)]).total_time, 12);
+        assert_eq!(simulate(&[(0, 0), (0, 3)]).total_time, 10);
+        assert_eq!(simulate(&[(0, 4), (0, 0)]).total_time, 10);
+    }
+
+    #[test]
+    fn timings_are_configurable() {
+        let report = Simulation::new(PlaneLayout::default())
+            .with_timings(Timings {
+                walk: 3,
+                stow: 0,
+                shuffle: 0,
+            })
+            .run(&[(10, 0), (128, 0)]);
+
+        assert_eq!(report.total_time, 33);
+    }
+
+    #[test]
+    fn arranges_boarding_orders() {
+        let layout = PlaneLayout::default();
+        let seats = [(1, 3), (2, 0), (3, 5), (4, 7), (5, 1)];
+
+        assert_eq!(
+            BoardingOrder::Manifest.arrange(&layout, &seats),
+            seats.to_vec()
+        );
+        assert_eq!(
+            BoardingOrder::WindowMiddleAisle.arrange(&layout, &seats),
+            vec![(2, 0), (4, 7), (5, 1), (3, 5), (1, 3)]
+        );
+
+        let random = BoardingOrder::Random(7).arrange(&layout, &seats);
+        let mut sorted = random.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, seats.to_vec());
+        assert_eq!(random, BoardingOrder::Random(7).arrange(&layout, &seats));
+        assert_eq!(random, vec![(5, 1), (2, 0), (4, 7), (1, 3), (3, 5)]);
+    }
+
+    #[test]
+    fn compares_boarding_orders_on_the_input() {
+        let layout = PlaneLayout::default();
+        let seats: Vec<(i32, i32)> = day5::read_batch("./day5.input", &layout)
+            .seats
+            .iter()
+            .map(|s| (s.row, s.column))
+            .collect();
+        let time = |order: BoardingOrder| simulate(&order.arrange(&layout, &seats)).total_time;
+
+        assert!(time(BoardingOrder::WindowMiddleAisle) < time(BoardingOrder::Random(1)));
+        assert!(time(BoardingOrder::Random(1)) < time(BoardingOrder::BackToFront));
+    }
+}
+
+#[cfg(test)]
+mod allocation_tests {
+    use super::day5;
+    use super::day5::allocation::{AllocationError, Allocator};
+    use super::day5::layout::PlaneLayout;
+    use super::day5::seatmap::{FreeSeat, Region, SeatMap};
+
+    /// Four rows of eight seats, with seats 2, 3, 4, 6 and 7 of row 1 and
+    /// seats 0, 1, 6 and 7 of row 2 free.
+    fn plane() -> SeatMap {
+        let mut map = SeatMap::new(PlaneLayout::new(2, 3).unwrap());
+        for row in 0..4 {
+            for column in 0..8 {
+                let free = match row {
+                    1 => [2, 3, 4, 6, 7].contains(&column),
+                    2 => [0, 1, 6, 7].contains(&column),
+                    _ => false,
+                };
+                if !free {
+                    map.occupy(row, column);
+                }
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn seats_a_pair_side_by_side() {
+        let map = plane();
+        let allocation = Allocator::new(&map).allocate(2).unwrap();
+
+        assert_eq!(allocation.seat_ids(), vec![10, 11]);
+        assert_eq!(allocation.passes(), vec!["FBLRL", "FBLRR"]);
+        assert_eq!(allocation.score, 0);
+    }
+
+    #[test]
+    fn crosses_the_aisle_when_it_has_to() {
+        let map = plane();
+        let allocation = Allocator::new(&map).allocate(3).unwrap();
+
+        assert_eq!(allocation.seat_ids(), vec![10, 11, 12]);
+        assert_eq!(allocation.score, -10);
+    }
+
+    #[test]
+    fn explains_why_a_group_cannot_sit_together() {
+        let map = plane();
+        let error = Allocator::new(&map).allocate(4).unwrap_err();
+
+        assert_eq!(
+            error,
+            AllocationError::NotTogether {
+                requested: 4,
+                largest_block: 3
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "4 seats requested but at most 3 free seats are side by side"
+        );
+        assert_eq!(
+            Allocator::new(&map).allocate(10),
+            Err(AllocationError::NotEnoughSeats {
+                requested: 10,
+                free: 9
+            })
+        );
+        assert_eq!(
+            Allocator::new(&map).allocate(0),
+            Err(AllocationError::EmptyGroup)
+        );
+    }
+
+    #[test]
+    fn splits_the_group_when_allowed() {
+        let map = plane();
+        let allocation = Allocator::new(&map).allow_split(true).allocate(4).unwrap();
+
+        assert_eq!(allocation.seat_ids(), vec![10, 11, 12, 14]);
+        assert_eq!(allocation.score, -11);
+    }
+
+    #[test]
+    fn split_candidates_do_not_depend_on_seat_order() {
+        fn spread(seats: &[FreeSeat], _: &PlaneLayout) -> i64 {
+            i64::from(seats[seats.len() - 1].column - seats[0].column)
+        }
+        fn one_per_row(seats: &[FreeSeat], _: &PlaneLayout) -> i64 {
+            -(seats.windows(2).filter(|p| p[0].row == p[1].row).count() as i64)
+        }
+        let map = plane();
+        let split = || Allocator::new(&map).allow_split(true);
+
+        assert_eq!(
+            split().with_scoring(spread).allocate(2).unwrap().seat_ids(),
+            vec![16, 23]
+        );
+        assert_eq!(
+            split()
+                .with_scoring(one_per_row)
+                .allocate(2)
+                .unwrap()
+                .seat_ids(),
+            vec![10, 16]
+        );
+        assert_eq!(
+            split().allocate(6).unwrap().seat_ids(),
+            vec![10, 11, 12, 14, 15, 16]
+        );
+    }
+
+    #[test]
+    fn scoring_is_configurable() {
+        fn towards_the_back(seats: &[FreeSeat], _: &PlaneLayout) -> i64 {
+            i64::from(seats[0].row)
+        }
+        let map = plane();
+        let allocation = Allocator::new(&map)
+            .with_scoring(towards_the_back)
+            .allocate(2)
+            .unwrap();
+
+        assert_eq!(allocation.seat_ids(), vec![16, 17]);
+
+        let preferred = 14;
+        let allocation = Allocator::new(&map)
+            .with_scoring(move |seats: &[FreeSeat], _: &PlaneLayout| {
+                -i64::from((seats[0].seat_id - preferred).abs())
+            })
+            .allocate(2)
+            .unwrap();
+
+        assert_eq!(allocation.seat_ids(), vec![14, 15]);
+    }
+
+    #[test]
+    fn every_free_seat_counts_by_default() {
+        let mut map = SeatMap::new(PlaneLayout::default());
+        map.occupy(10, 0);
+
+        assert_eq!(
+            Allocator::new(&map).allocate(2).unwrap().seat_ids(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            Allocator::new(&map).allocate(2000),
+            Err(AllocationError::NotEnoughSeats {
+                requested: 2000,
+                free: 1023
+            })
+        );
+    }
+
+    #[test]
+    fn only_my_seat_is_left_on_the_input() {
+        let map = day5::read_seat_map("./day5.input", PlaneLayout::default());
+        let in_service = || Allocator::new(&map).exclude_regions(&[Region::Front, Region::Back]);
+
+        assert_eq!(in_service().allocate(1).unwrap().seat_ids(), vec![565]);
+        assert_eq!(
+            in_service().allocate(2),
+            Err(AllocationError::NotEnoughSeats {
+                requested: 2,
+                free: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Answer sets as bitmasks, one bit per question, so that group operations
+//! are bitwise.
+
+use std::fmt;
+
+/// The questions one person, or a group, answered.
+pub trait AnswerSet: Copy + Default + Eq {
+    /// The set of a person's answers, or `None` if a question is outside
+    /// the set's alphabet.
+    fn from_answers(answers: &str) -> Option<Self>;
+    fn union(self, other: Self) -> Self;
+    fn intersection(self, other: Self) -> Self;
+    fn symmetric_difference(self, other: Self) -> Self;
+    /// Questions of `self` not in `other`.
+    fn difference(self, other: Self) -> Self;
+    fn len(&self) -> usize;
+    fn contains(&self, question: char) -> bool;
+    /// The questions in alphabetical order.
+    fn questions(&self) -> Vec<char>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Questions `a` to `z` in a `u32`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Answers(pub u32);
+
+impl AnswerSet for Answers {
+    fn from_answers(answers: &str) -> Option<Answers> {
+        answers.chars().try_fold(Answers(0), |set, q| {
+            if q.is_ascii_lowercase() {
+                Some(Answers(set.0 | 1 << (q as u32 - 'a' as u32)))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn union(self, other: Answers) -> Answers {
+        Answers(self.0 | other.0)
+    }
+
+    fn intersection(self, other: Answers) -> Answers {
+        Answers(self.0 & other.0)
+    }
+
+    fn symmetric_difference(self, other: Answers) -> Answers {
+        Answers(self.0 ^ other.0)
+    }
+
+    fn difference(self, other: Answers) -> Answers {
+        Answers(self.0 & !other.0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn contains(&self, question: char) -> bool {
+        question.is_ascii_lowercase() && self.0 >> (question as u32 - 'a' as u32) & 1 == 1
+    }
+
+    fn questions(&self) -> Vec<char> {
+        ('a'..='z').filter(|q| self.contains(*q)).collect()
+    }
+}
+
+/// Any question below U+0100, for forms using more than `a` to `z`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WideAnswers(pub [u64; 4]);
+
+impl WideAnswers {
+    fn zip(self, other: WideAnswers, op: fn(u64, u64) -> u64) -> WideAnswers {
+        let mut words = [0; 4];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = op(self.0[i], other.0[i]);
+        }
+        WideAnswers(words)
+    }
+}
+
+impl AnswerSet for WideAnswers {
+    fn from_answers(answers: &str) -> Option<WideAnswers> {
+        answers
+            .chars()
+            .try_fold(WideAnswers::default(), |mut set, q| {
+                let bit = q as usize;
+                if bit >= 256 {
+                    return None;
+                }
+                set.0[bit / 64] |= 1 << (bit % 64);
+                Some(set)
+            })
+    }
+
+    fn union(self, other: WideAnswers) -> WideAnswers {
+        self.zip(other, |a, b| a | b)
+    }
+
+    fn intersection(self, other: WideAnswers) -> WideAnswers {
+        self.zip(other, |a, b| a & b)
+    }
+
+    fn symmetric_difference(self, other: WideAnswers) -> WideAnswers {
+        self.zip(other, |a, b| a ^ b)
+    }
+
+    fn difference(self, other: WideAnswers) -> WideAnswers {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn contains(&self, question: char) -> bool {
+        let bit = question as usize;
+        bit < 256 && self.0[bit / 64] >> (bit % 64) & 1 == 1
+    }
+
+    fn questions(&self) -> Vec<char> {
+        (0..=255u8)
+            .map(char::from)
+            .filter(|q| self.contains(*q))
+            .collect()
+    }
+}
+
+/// Each person's answers, or `None` if one of them is outside the alphabet.
+pub fn parse_group<S: AnswerSet>(group: &[String]) -> Option<Vec<S>> {
+    group.iter().map(|person| S::from_answers(person)).collect()
+}
+
+/// A group's answers, parsed once into the narrowest set type holding every
+/// question.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Group {
+    /// Questions `a` to `z`.
+    Narrow(Vec<Answers>),
+    /// Questions below U+0100.
+    Wide(Vec<WideAnswers>),
+}
+
+impl Group {
+    /// The answers of a group, one string per person.
+    pub fn parse(people: &[String]) -> Result<Group, UnsupportedQuestion> {
+        for (i, person) in people.iter().enumerate() {
+            if let Some(question) = person.chars().find(|q| *q > '\u{ff}') {
+                return Err(UnsupportedQuestion {
+                    line: i + 1,
+                    question,
+                });
+            }
+        }
+        Ok(match parse_group(people) {
+            Some(people) => Group::Narrow(people),
+            None => Group::Wide(parse_group(people).unwrap_or_default()),
+        })
+    }
+
+    /// Number of people in the group.
+    pub fn size(&self) -> usize {
+        match self {
+            Group::Narrow(people) => people.len(),
+            Group::Wide(people) => people.len(),
+        }
+    }
+}
+
+/// A question no answer set holds. The line is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedQuestion {
+    pub line: usize,
+    pub question: char,
+}
+
+impl fmt::Display for UnsupportedQuestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: question {:?} is beyond U+00FF",
+            self.line, self.question
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedQuestion {}
+
+/// Questions anyone answered.
+pub fn anyone<S: AnswerSet>(group: &[S]) -> S {
+    group.iter().fold(S::default(), |set, p| set.union(*p))
+}
+
+/// Questions everyone answered; none for an empty group.
+pub fn everyone<S: AnswerSet>(group: &[S]) -> S {
+    match group.split_first() {
+        Some((first, rest)) => rest.iter().fold(*first, |set, p| set.intersection(*p)),
+        None => S::default(),
+    }
+}
+
+/// Questions answered by an odd number of people.
+pub fn odd<S: AnswerSet>(group: &[S]) -> S {
+    group
+        .iter()
+        .fold(S::default(), |set, p| set.symmetric_difference(*p))
+}
+
+/// Questions answered by one person only.
+pub fn exactly_one<S: AnswerSet>(group: &[S]) -> S {
+    let mut once = S::default();
+    let mut more = S::default();
+    for person in group {
+        more = more.union(once.intersection(*person));
+        once = once.union(*person).difference(more);
+    }
+    once
+}
+
+/// Questions answered by at least `k` people; with `k` of 0, questions
+/// anyone answered.
+pub fn at_least<S: AnswerSet>(group: &[S], k: usize) -> S {
+    if k > group.len() {
+        return S::default();
+    }
+    // levels[i] holds the questions answered by more than i people so far.
+    let mut levels: Vec<S> = vec![S::default(); k.max(1)];
+    for person in group {
+        for i in (0..levels.len()).rev() {
+            let below = if i == 0 {
+                *person
+            } else {
+                levels[i - 1].intersection(*person)
+            };
+            levels[i] = levels[i].union(below);
+        }
+    }
+    levels[k.max(1) - 1]
+}
@@ -0,0 +1,77 @@
+use super::answers::{self, AnswerSet, Group};
+
+/// A question set to compute for each group from the answers of its people.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// Questions anyone answered.
+    Union,
+    /// Questions everyone answered.
+    Intersection,
+    /// Questions answered by an odd number of people, which is the symmetric
+    /// difference of everyone's answers.
+    SymmetricDifference,
+    /// Questions answered by at least this many people.
+    AtLeast(usize),
+    /// Questions answered by at least this percentage of the group.
+    AtLeastPercent(u32),
+    /// Questions answered by one person only.
+    ExactlyOne,
+}
+
+impl Query {
+    /// The questions of a group matching the query, in increasing order. A
+    /// person answering a question twice counts once.
+    pub fn answers(&self, group: &Group) -> Vec<char> {
+        match group {
+            Group::Narrow(people) => self.select(people).questions(),
+            Group::Wide(people) => self.select(people).questions(),
+        }
+    }
+
+    /// How many questions of a group match the query.
+    pub fn count(&self, group: &Group) -> usize {
+        match group {
+            Group::Narrow(people) => self.select(people).len(),
+            Group::Wide(people) => self.select(people).len(),
+        }
+    }
+
+    /// The questions of a group matching the query, with bitwise operations.
+    pub fn select<S: AnswerSet>(&self, group: &[S]) -> S {
+        match *self {
+            Query::Union => answers::anyone(group),
+            Query::Intersection => answers::everyone(group),
+            Query::SymmetricDifference => answers::odd(group),
+            Query::AtLeast(k) => answers::at_least(group, k),
+            Query::AtLeastPercent(percent) => answers::at_least(
+                group,
+                (percent as usize).saturating_mul(group.len()).div_ceil(100),
+            ),
+            Query::ExactlyOne => answers::exactly_one(group),
+        }
+    }
+}
+
+/// The questions matching a query in each group, in input order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    pub groups: Vec<Vec<char>>,
+}
+
+impl QueryResult {
+    pub fn new(groups: &[Group], query: Query) -> QueryResult {
+        QueryResult {
+            groups: groups.iter().map(|g| query.answers(g)).collect(),
+        }
+    }
+
+    /// How many questions matched in each group.
+    pub fn counts(&self) -> Vec<usize> {
+        self.groups.iter().map(|g| g.len()).collect()
+    }
+
+    /// Matching questions over all groups, counted once per group.
+    pub fn total(&self) -> usize {
+        self.groups.iter().map(|g| g.len()).sum()
+    }
+}
@@ -0,0 +1,170 @@
+use super::answers::{self, AnswerSet, Group};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// How one question was answered across all groups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionStats {
+    pub question: char,
+    /// People who answered it.
+    pub people: usize,
+    /// Groups where anyone answered it.
+    pub groups: usize,
+    /// Groups where everyone answered it.
+    pub unanimous_groups: usize,
+}
+
+impl QuestionStats {
+    /// Share of the groups answering the question where everyone did.
+    pub fn unanimity_rate(&self) -> f64 {
+        if self.groups == 0 {
+            0.0
+        } else {
+            self.unanimous_groups as f64 / self.groups as f64
+        }
+    }
+}
+
+/// Answers per question over a whole batch of groups. Questions `a` to `z`
+/// are always listed, so that those nobody answered show up too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    /// In alphabetical order.
+    pub questions: Vec<QuestionStats>,
+    /// Number of groups of each size.
+    pub group_sizes: BTreeMap<usize, usize>,
+}
+
+impl Statistics {
+    pub fn new(groups: &[Group]) -> Statistics {
+        let mut questions: BTreeMap<char, QuestionStats> =
+            ('a'..='z').map(|q| (q, QuestionStats::empty(q))).collect();
+        let mut group_sizes: BTreeMap<usize, usize> = BTreeMap::new();
+
+        for group in groups {
+            *group_sizes.entry(group.size()).or_insert(0) += 1;
+            match group {
+                Group::Narrow(people) => count_group(&mut questions, people),
+                Group::Wide(people) => count_group(&mut questions, people),
+            }
+        }
+
+        Statistics {
+            questions: questions.into_values().collect(),
+            group_sizes,
+        }
+    }
+
+    /// The questions most people answered, several on ties.
+    pub fn most_answered(&self) -> Vec<char> {
+        self.top_by(|s| s.people)
+    }
+
+    /// The questions most groups answered unanimously, several on ties.
+    pub fn most_unanimous(&self) -> Vec<char> {
+        self.top_by(|s| s.unanimous_groups)
+    }
+
+    pub fn never_answered(&self) -> Vec<char> {
+        self.questions
+            .iter()
+            .filter(|s| s.people == 0)
+            .map(|s| s.question)
+            .collect()
+    }
+
+    /// Share of the questions answered in a group which everyone in the
+    /// group answered, over all groups.
+    pub fn unanimity_rate(&self) -> f64 {
+        let answered: usize = self.questions.iter().map(|s| s.groups).sum();
+        let unanimous: usize = self.questions.iter().map(|s| s.unanimous_groups).sum();
+        if answered == 0 {
+            0.0
+        } else {
+            unanimous as f64 / answered as f64
+        }
+    }
+
+    /// One row per question:
+    ///
+    /// ```text
+    /// question,people,groups,unanimous_groups,unanimity_rate
+    /// a,7,3,2,0.6667
+    /// ```
+    pub fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "question,people,groups,unanimous_groups,unanimity_rate"
+        )?;
+        for stats in &self.questions {
+            writeln!(
+                out,
+                "{},{},{},{},{:.4}",
+                csv_cell(stats.question),
+                stats.people,
+                stats.groups,
+                stats.unanimous_groups,
+                stats.unanimity_rate()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// One row per group size: `group_size,groups`.
+    pub fn write_group_sizes_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "group_size,groups")?;
+        for (size, groups) in &self.group_sizes {
+            writeln!(out, "{},{}", size, groups)?;
+        }
+        Ok(())
+    }
+
+    fn top_by<F>(&self, key: F) -> Vec<char>
+    where
+        F: Fn(&QuestionStats) -> usize,
+    {
+        let max = self.questions.iter().map(&key).max().unwrap_or(0);
+        if max == 0 {
+            return Vec::new();
+        }
+        self.questions
+            .iter()
+            .filter(|s| key(s) == max)
+            .map(|s| s.question)
+            .collect()
+    }
+}
+
+impl QuestionStats {
+    fn empty(question: char) -> QuestionStats {
+        QuestionStats {
+            question,
+            people: 0,
+            groups: 0,
+            unanimous_groups: 0,
+        }
+    }
+}
+
+/// Adds the answers of one group to the per-question counts.
+fn count_group<S: AnswerSet>(questions: &mut BTreeMap<char, QuestionStats>, people: &[S]) {
+    let unanimous = answers::everyone(people);
+    for q in answers::anyone(people).questions() {
+        let stats = questions
+            .entry(q)
+            .or_insert_with(|| QuestionStats::empty(q));
+        stats.people += people.iter().filter(|p| p.contains(q)).count();
+        stats.groups += 1;
+        if unanimous.contains(q) {
+            stats.unanimous_groups += 1;
+        }
+    }
+}
+
+fn csv_cell(question: char) -> String {
+    match question {
+        ',' | '\n' | '\r' => format!("\"{}\"", question),
+        '"' => String::from("\"\"\"\""),
+        _ => question.to_string(),
+    }
+}
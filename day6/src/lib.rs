@@ -1,5 +1,13 @@
 pub mod day6 {
-    use std::{collections::HashMap, io::BufRead};
+    pub mod answers;
+    pub mod query;
+    pub mod stats;
+
+    use answers::{Group, UnsupportedQuestion};
+    use query::{Query, QueryResult};
+    use stats::Statistics;
+    use std::collections::BTreeSet;
+    use std::io::BufRead;
 
     pub fn identify_questions_total(input: &mut dyn BufRead) -> i32 {
         identify_questions_anyone_answered(input).iter().sum()
@@ -10,49 +18,85 @@ pub mod day6 {
     }
 
     pub fn identify_questions_anyone_answered(input: &mut dyn BufRead) -> Vec<i32> {
-        return identify_question(input, identify_questions_which_anyone_answered);
+        identify_question(input, identify_questions_which_anyone_answered)
     }
 
     pub fn identify_questions_everyone_answered(input: &mut dyn BufRead) -> Vec<i32> {
-        return identify_question(input, identify_questions_which_everyone_answered);
+        identify_question(input, identify_questions_which_everyone_answered)
     }
 
-    pub fn identify_question(input: &mut dyn BufRead, identifier: fn(&Vec<String>) -> i32) -> Vec<i32> {
+    pub fn identify_question(
+        input: &mut dyn BufRead,
+        identifier: fn(&Vec<String>) -> i32,
+    ) -> Vec<i32> {
         let groups = parse_groups(input);
 
-        return groups
-            .iter()
-            .map(|g| identifier(g))
-            .collect();
+        groups.iter().map(identifier).collect()
+    }
+
+    /// The questions of each group matching a query, with totals.
+    pub fn query(
+        input: &mut dyn BufRead,
+        query: Query,
+    ) -> Result<QueryResult, UnsupportedQuestion> {
+        Ok(QueryResult::new(&parse_answer_groups(input)?, query))
+    }
+
+    /// Per-question statistics over all groups.
+    pub fn statistics(input: &mut dyn BufRead) -> Result<Statistics, UnsupportedQuestion> {
+        Ok(Statistics::new(&parse_answer_groups(input)?))
     }
 
+    /// Splits the forms into groups separated by blank lines and parses each
+    /// person's answers into a bitmask.
+    pub fn parse_answer_groups(input: &mut dyn BufRead) -> Result<Vec<Group>, UnsupportedQuestion> {
+        let mut groups = Vec::new();
+        let mut first_line = 1;
+        for people in parse_groups(input) {
+            let group = Group::parse(&people).map_err(|e| UnsupportedQuestion {
+                line: first_line + e.line - 1,
+                ..e
+            })?;
+            first_line += people.len() + 1;
+            groups.push(group);
+        }
+        Ok(groups)
+    }
+
+    /// The groups separated by blank lines, one string of answers per person.
     fn parse_groups(input: &mut dyn BufRead) -> Vec<Vec<String>> {
         let lines: Vec<String> = input.lines().map(|line| line.unwrap()).collect();
-        return lines
-            .split(|line| line == "")
-            .map(|g| parse_group(g))
-            .collect();
+        lines
+            .split(|line| line.is_empty())
+            .map(|group| group.to_vec())
+            .collect()
     }
 
-    fn parse_group(lines: &[String]) -> Vec<String> {
-        return lines.iter().map(|s| String::from(s)).collect();
+    /// Each person's answers as a set, for groups with questions beyond
+    /// U+00FF, which do not fit the answer masks.
+    fn answer_sets(group: &[String]) -> impl Iterator<Item = BTreeSet<char>> + '_ {
+        group.iter().map(|person| person.chars().collect())
     }
 
+    // Both identifiers take `&Vec<String>` to fit `identify_question`.
+    #[allow(clippy::ptr_arg)]
     fn identify_questions_which_anyone_answered(group: &Vec<String>) -> i32 {
-        let mut questions: Vec<char> = group.join("").chars().collect();
-        questions.sort();
-        questions.dedup();
-        return questions.len() as i32;
+        match Group::parse(group) {
+            Ok(group) => Query::Union.count(&group) as i32,
+            Err(_) => answer_sets(group)
+                .fold(BTreeSet::new(), |anyone, person| &anyone | &person)
+                .len() as i32,
+        }
     }
 
+    #[allow(clippy::ptr_arg)]
     fn identify_questions_which_everyone_answered(group: &Vec<String>) -> i32 {
-        let group_size = group.len() as i32;
-        let stats: HashMap<char, i32> = group.join("").chars().fold(HashMap::new(), |mut hm, c| {
-            *hm.entry(c).or_insert(0) += 1;
-            hm
-        });
-
-        return stats.iter().filter(|q| *q.1 == group_size).count() as i32;
+        match Group::parse(group) {
+            Ok(group) => Query::Intersection.count(&group) as i32,
+            Err(_) => answer_sets(group)
+                .reduce(|everyone, person| &everyone & &person)
+                .map_or(0, |everyone| everyone.len() as i32),
+        }
     }
 }
 
@@ -163,10 +207,337 @@ mod tests {
     #[test]
     fn day6_part2_input() {
         let mut f = BufReader::new(File::open("./day6.input").unwrap());
-        assert_eq!(day6::identify_questions_everyone_answered_total(&mut f), 3229);
+        assert_eq!(
+            day6::identify_questions_everyone_answered_total(&mut f),
+            3229
+        );
     }
 
     fn read_from_string(s: &str) -> &[u8] {
         s.as_bytes()
     }
 }
+
+#[cfg(test)]
+mod query_tests {
+    use super::day6;
+    use super::day6::answers::Group;
+    use super::day6::query::Query;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn answers(query: Query, group: &[&str]) -> String {
+        let group: Vec<String> = group.iter().map(|p| String::from(*p)).collect();
+        query
+            .answers(&Group::parse(&group).unwrap())
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let group = ["abcx", "abcy", "abcz"];
+
+        assert_eq!(answers(Query::Union, &group), "abcxyz");
+        assert_eq!(answers(Query::Intersection, &group), "abc");
+    }
+
+    #[test]
+    fn symmetric_difference() {
+        assert_eq!(answers(Query::SymmetricDifference, &["ab", "bc"]), "ac");
+        assert_eq!(
+            answers(Query::SymmetricDifference, &["ab", "bc", "b"]),
+            "abc"
+        );
+    }
+
+    #[test]
+    fn answered_by_at_least() {
+        let group = ["abc", "ab", "a", "d"];
+
+        assert_eq!(answers(Query::AtLeast(2), &group), "ab");
+        assert_eq!(answers(Query::AtLeast(3), &group), "a");
+        assert_eq!(answers(Query::AtLeastPercent(50), &group), "ab");
+        assert_eq!(answers(Query::AtLeastPercent(51), &group), "a");
+        assert_eq!(answers(Query::AtLeastPercent(0), &group), "abcd");
+    }
+
+    #[test]
+    fn answered_by_exactly_one() {
+        assert_eq!(answers(Query::ExactlyOne, &["abc", "ab", "a", "d"]), "cd");
+    }
+
+    #[test]
+    fn repeated_answers_count_once() {
+        assert_eq!(answers(Query::ExactlyOne, &["aa", "b"]), "ab");
+        assert_eq!(answers(Query::AtLeast(2), &["aa", "b"]), "");
+    }
+
+    #[test]
+    fn per_group_results_and_totals() {
+        let mut f = BufReader::new(File::open("./sample.input").unwrap());
+        let result = day6::query(&mut f, Query::AtLeast(2)).unwrap();
+
+        assert_eq!(
+            result.groups,
+            vec![vec![], vec![], vec!['a'], vec!['a'], vec![]]
+        );
+        assert_eq!(result.counts(), vec![0, 0, 1, 1, 0]);
+        assert_eq!(result.total(), 2);
+    }
+
+    #[test]
+    fn queries_agree_with_anyone_and_everyone() {
+        let total = |query: Query| {
+            let mut f = BufReader::new(File::open("./day6.input").unwrap());
+            day6::query(&mut f, query).unwrap().total()
+        };
+
+        assert_eq!(total(Query::Union), 6437);
+        assert_eq!(total(Query::Intersection), 3229);
+        assert_eq!(total(Query::AtLeastPercent(100)), 3229);
+        assert_eq!(total(Query::AtLeast(1)), 6437);
+    }
+}
+
+#[cfg(test)]
+mod answers_tests {
+    use super::day6;
+    use super::day6::answers::{self, AnswerSet, Answers, Group, UnsupportedQuestion, WideAnswers};
+    use super::day6::query::Query;
+
+    fn group<S: AnswerSet>(people: &[&str]) -> Vec<S> {
+        people.iter().map(|p| S::from_answers(p).unwrap()).collect()
+    }
+
+    #[test]
+    fn one_bit_per_question() {
+        assert_eq!(
+            Answers::from_answers("abz"),
+            Some(Answers(0b10_0000_0000_0000_0000_0000_0011))
+        );
+        assert_eq!(Answers::from_answers("aa"), Some(Answers(1)));
+        assert_eq!(Answers::from_answers("aB"), None);
+        assert_eq!(
+            Answers::from_answers("abz").unwrap().questions(),
+            vec!['a', 'b', 'z']
+        );
+        assert!(Answers::default().is_empty());
+    }
+
+    #[test]
+    fn wide_sets_cover_more_questions() {
+        let set = WideAnswers::from_answers("aZ9\u{e9}").unwrap();
+
+        assert_eq!(set.len(), 4);
+        assert!(set.contains('\u{e9}'));
+        assert!(!set.contains('b'));
+        assert_eq!(set.questions(), vec!['9', 'Z', 'a', '\u{e9}']);
+        assert_eq!(WideAnswers::from_answers("\u{1f384}"), None);
+    }
+
+    #[test]
+    fn group_operations() {
+        let people: Vec<Answers> = group(&["abc", "ab", "a", "d"]);
+
+        assert_eq!(
+            answers::anyone(&people).questions(),
+            vec!['a', 'b', 'c', 'd']
+        );
+        assert_eq!(answers::everyone(&people), Answers(0));
+        assert_eq!(answers::everyone(&people[..3]).questions(), vec!['a']);
+        assert_eq!(answers::odd(&people).questions(), vec!['a', 'c', 'd']);
+        assert_eq!(answers::exactly_one(&people).questions(), vec!['c', 'd']);
+        assert_eq!(answers::at_least(&people, 2).questions(), vec!['a', 'b']);
+        assert_eq!(answers::at_least(&people, 3).questions(), vec!['a']);
+        assert_eq!(answers::at_least(&people, 0), answers::anyone(&people));
+        assert_eq!(answers::everyone::<Answers>(&[]), Answers(0));
+    }
+
+    #[test]
+    fn wide_and_narrow_sets_agree() {
+        let people = ["abcq", "abq", "aq", "dq", "bq"];
+        let narrow: Vec<Answers> = group(&people);
+        let wide: Vec<WideAnswers> = group(&people);
+        for query in [
+            Query::Union,
+            Query::Intersection,
+            Query::SymmetricDifference,
+            Query::AtLeast(2),
+            Query::AtLeastPercent(60),
+            Query::ExactlyOne,
+        ] {
+            assert_eq!(
+                query.select(&narrow).questions(),
+                query.select(&wide).questions()
+            );
+        }
+    }
+
+    #[test]
+    fn groups_use_the_narrowest_sets() {
+        let group = |people: &[&str]| {
+            Group::parse(
+                &people
+                    .iter()
+                    .map(|p| String::from(*p))
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        assert_eq!(
+            group(&["ab", "b"]),
+            Ok(Group::Narrow(vec![Answers(0b11), Answers(0b10)]))
+        );
+        assert!(matches!(group(&["a", "\u{e9}"]), Ok(Group::Wide(_))));
+        assert_eq!(
+            group(&["a", "\u{e9}"]).map(|g| (Query::Union.count(&g), g.size())),
+            Ok((2, 2))
+        );
+    }
+
+    #[test]
+    fn rejects_questions_beyond_latin1() {
+        let error = day6::parse_answer_groups(&mut "ab\n\na\nb\u{1f384}\n".as_bytes()).unwrap_err();
+
+        assert_eq!(
+            error,
+            UnsupportedQuestion {
+                line: 4,
+                question: '\u{1f384}'
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 4: question '\u{1f384}' is beyond U+00FF"
+        );
+    }
+
+    #[test]
+    fn counts_questions_beyond_latin1_without_masks() {
+        let input = "ab\u{65e5}\nc\n\nx\u{65e5}\n\u{65e5}y\n\nab\nb";
+
+        assert_eq!(
+            day6::identify_questions_anyone_answered(&mut input.as_bytes()),
+            vec![4, 3, 2]
+        );
+        assert_eq!(
+            day6::identify_questions_everyone_answered(&mut input.as_bytes()),
+            vec![0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn at_least_more_than_the_group_is_nothing() {
+        let people: Vec<Answers> = group(&["ab", "b"]);
+
+        assert_eq!(answers::at_least(&people, 3), Answers(0));
+        assert_eq!(answers::at_least(&people, usize::MAX), Answers(0));
+        assert_eq!(Query::AtLeast(usize::MAX).select(&people), Answers(0));
+        assert_eq!(Query::AtLeastPercent(u32::MAX).select(&people), Answers(0));
+        assert_eq!(answers::at_least::<Answers>(&[], 0), Answers(0));
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::day6;
+    use super::day6::stats::Statistics;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn sample() -> Statistics {
+        let mut f = BufReader::new(File::open("./sample.input").unwrap());
+        day6::statistics(&mut f).unwrap()
+    }
+
+    #[test]
+    fn counts_per_question() {
+        let stats = sample();
+        let a = &stats.questions[0];
+
+        assert_eq!(stats.questions.len(), 26);
+        assert_eq!(
+            (a.question, a.people, a.groups, a.unanimous_groups),
+            ('a', 8, 4, 3)
+        );
+        assert_eq!(stats.most_answered(), vec!['a']);
+        assert_eq!(stats.most_unanimous(), vec!['a']);
+        assert_eq!(stats.never_answered(), ('d'..='z').collect::<Vec<char>>());
+    }
+
+    #[test]
+    fn group_sizes_and_unanimity() {
+        let stats = sample();
+
+        assert_eq!(
+            stats
+                .group_sizes
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<(usize, usize)>>(),
+            vec![(1, 2), (2, 1), (3, 1), (4, 1)]
+        );
+        assert!((stats.unanimity_rate() - 6.0 / 11.0).abs() < 1e-9);
+        assert!((stats.questions[1].unanimity_rate() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exports_csv() {
+        let stats = sample();
+        let mut questions: Vec<u8> = Vec::new();
+        let mut sizes: Vec<u8> = Vec::new();
+        stats.write_csv(&mut questions).unwrap();
+        stats.write_group_sizes_csv(&mut sizes).unwrap();
+        let questions = String::from_utf8(questions).unwrap();
+
+        assert_eq!(
+            questions.lines().take(5).collect::<Vec<&str>>(),
+            vec![
+                "question,people,groups,unanimous_groups,unanimity_rate",
+                "a,8,4,3,0.7500",
+                "b,4,4,2,0.5000",
+                "c,3,3,1,0.3333",
+                "d,0,0,0,0.0000",
+            ]
+        );
+        assert_eq!(questions.lines().count(), 27);
+        assert_eq!(
+            String::from_utf8(sizes).unwrap(),
+            "group_size,groups\n1,2\n2,1\n3,1\n4,1\n"
+        );
+    }
+
+    #[test]
+    fn other_questions_are_listed_after_the_alphabet() {
+        let groups = day6::parse_answer_groups(&mut "a,\n,".as_bytes()).unwrap();
+        let stats = Statistics::new(&groups);
+        let mut out: Vec<u8> = Vec::new();
+        stats.write_csv(&mut out).unwrap();
+
+        assert_eq!(stats.questions[0].question, ',');
+        assert_eq!(stats.most_unanimous(), vec![',']);
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("\n\",\",2,1,1,1.0000\n"));
+    }
+
+    #[test]
+    fn empty_input() {
+        let stats = Statistics::new(&[]);
+
+        assert!(stats.most_answered().is_empty());
+        assert_eq!(stats.never_answered().len(), 26);
+        assert_eq!(stats.unanimity_rate(), 0.0);
+    }
+
+    #[test]
+    fn input_statistics_agree_with_the_totals() {
+        let mut f = BufReader::new(File::open("./day6.input").unwrap());
+        let stats = day6::statistics(&mut f).unwrap();
+        let anyone: usize = stats.questions.iter().map(|s| s.groups).sum();
+        let everyone: usize = stats.questions.iter().map(|s| s.unanimous_groups).sum();
+
+        assert_eq!((anyone, everyone), (6437, 3229));
+    }
+}